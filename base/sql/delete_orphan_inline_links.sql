@@ -0,0 +1,4 @@
+DELETE FROM
+  inline_links
+WHERE
+  referrer_url NOT IN (SELECT url FROM resources);
@@ -0,0 +1,4 @@
+DELETE FROM
+  reference_links
+WHERE
+  referrer_url NOT IN (SELECT url FROM resources);
@@ -0,0 +1,4 @@
+DELETE FROM
+  tags
+WHERE
+  target_url NOT IN (SELECT url FROM resources);
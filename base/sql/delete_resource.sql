@@ -0,0 +1,4 @@
+DELETE FROM
+  resources
+WHERE
+  url = :url;
@@ -0,0 +1,4 @@
+DELETE FROM
+  revision_links
+WHERE
+  url = :url;
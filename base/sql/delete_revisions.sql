@@ -0,0 +1,4 @@
+DELETE FROM
+  revisions
+WHERE
+  url = :url;
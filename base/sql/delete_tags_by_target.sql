@@ -0,0 +1,4 @@
+DELETE FROM
+  tags
+WHERE
+  target_url = :target_url;
@@ -0,0 +1,5 @@
+DELETE FROM
+  contents
+WHERE
+  cid NOT IN (SELECT cid FROM resources WHERE cid IS NOT NULL)
+  AND cid NOT IN (SELECT cid FROM revisions WHERE cid IS NOT NULL);
@@ -0,0 +1,4 @@
+INSERT OR IGNORE INTO contents
+  (cid, content)
+VALUES
+  (:cid, :content);
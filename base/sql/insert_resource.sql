@@ -1,4 +1,4 @@
 INSERT OR REPLACE INTO resources
-  (url, title, description, cid, icon, image)
+  (url, title, description, cid, icon, image, language, has_content, created_at, modified_at, ingested_at)
 VALUES
-  (:url, :title, :description, :cid, :icon, :image);
+  (:url, :title, :description, :cid, :icon, :image, :language, :has_content, :created_at, :modified_at, :ingested_at);
@@ -0,0 +1,4 @@
+INSERT INTO revisions
+  (url, version, cid, title, description, recorded_at)
+VALUES
+  (:url, :version, :cid, :title, :description, :recorded_at);
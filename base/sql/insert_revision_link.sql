@@ -0,0 +1,4 @@
+INSERT INTO revision_links
+  (url, version, target_url, name)
+VALUES
+  (:url, :version, :target_url, :name);
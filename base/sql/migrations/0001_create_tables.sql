@@ -1,5 +1,3 @@
-PRAGMA foreign_keys = ON;
-
 CREATE TABLE IF NOT EXISTS resources (
   url NOT NULL,
   title Text,
@@ -112,6 +110,3 @@ INNER JOIN
   resources
 ON
   reference_links.referrer_url = resources.url;
-
-
-PRAGMA user_version = 1;
\ No newline at end of file
@@ -0,0 +1,68 @@
+ALTER TABLE resources ADD COLUMN created_at Integer;
+ALTER TABLE resources ADD COLUMN modified_at Integer;
+
+-- Resources ingested before timestamps were tracked have only ingest time
+-- recorded, which is the best estimate we have.
+UPDATE resources
+SET
+  created_at = ingested_at,
+  modified_at = ingested_at;
+
+CREATE INDEX IF NOT EXISTS resources_idx_modified_at ON
+  resources (modified_at);
+
+DROP VIEW IF EXISTS view_links;
+CREATE VIEW IF NOT EXISTS
+  view_links
+AS
+SELECT
+  referrer_url,
+  resources.title as referrer_title,
+  resources.description as referrer_description,
+  resources.cid as referrer_cid,
+  resources.icon as referrer_icon,
+  resources.image as referrer_image,
+  resources.created_at as referrer_created_at,
+  resources.modified_at as referrer_modified_at,
+  resources.ingested_at as referrer_ingested_at,
+  referrer_fragment,
+  referrer_location,
+
+  target_url,
+  NULL as identifier,
+  name,
+  inline_links.title as title,
+  0 as kind
+FROM
+  inline_links
+INNER JOIN
+  resources
+ON
+  inline_links.referrer_url = resources.url
+
+UNION
+
+SELECT
+  referrer_url,
+  resources.title as referrer_title,
+  resources.description as referrer_description,
+  resources.cid as referrer_cid,
+  resources.icon as referrer_icon,
+  resources.image as referrer_image,
+  resources.created_at as referrer_created_at,
+  resources.modified_at as referrer_modified_at,
+  resources.ingested_at as referrer_ingested_at,
+  referrer_fragment,
+  referrer_location,
+
+  target_url,
+  identifier,
+  name,
+  reference_links.title AS title,
+  1 as kind
+FROM
+  reference_links
+INNER JOIN
+  resources
+ON
+  reference_links.referrer_url = resources.url;
@@ -0,0 +1,68 @@
+CREATE TABLE IF NOT EXISTS revisions (
+  url Text NOT NULL,
+  version Integer NOT NULL,
+  cid Text,
+  title Text,
+  description Text,
+  recorded_at Integer NOT NULL,
+
+  PRIMARY KEY (url, version)
+)
+WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS revision_links (
+  url Text NOT NULL,
+  version Integer NOT NULL,
+  target_url Text NOT NULL,
+  name Text NOT NULL,
+
+  PRIMARY KEY (url, version, target_url),
+  FOREIGN KEY(url, version) REFERENCES revisions(url, version)
+)
+WITHOUT ROWID;
+
+-- Current state of the existing resources becomes their first revision.
+INSERT INTO revisions
+  (url, version, cid, title, description, recorded_at)
+SELECT
+  url,
+  1,
+  cid,
+  title,
+  description,
+  COALESCE(modified_at, ingested_at, 0)
+FROM
+  resources;
+
+-- Revision keeps the name of the first link to each target. Links are stored
+-- in the order they were submitted, but inline and reference links are kept
+-- in separate tables, so inline ones are assumed to come first.
+INSERT INTO revision_links
+  (url, version, target_url, name)
+SELECT
+  referrer_url,
+  1,
+  target_url,
+  name
+FROM
+  (
+    SELECT
+      referrer_url,
+      target_url,
+      name,
+      ROW_NUMBER() OVER (
+        PARTITION BY referrer_url, target_url
+        ORDER BY kind, position
+      ) AS occurrence
+    FROM
+      (
+        SELECT referrer_url, target_url, name, 0 AS kind, rowid AS position
+        FROM inline_links
+        UNION ALL
+        SELECT referrer_url, target_url, name, 1 AS kind, rowid AS position
+        FROM reference_links
+      )
+  )
+WHERE
+  occurrence = 1
+  AND referrer_url IN (SELECT url FROM resources);
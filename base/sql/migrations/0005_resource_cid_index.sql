@@ -0,0 +1,2 @@
+CREATE INDEX IF NOT EXISTS resources_idx_cid ON
+  resources (cid);
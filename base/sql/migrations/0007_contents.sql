@@ -0,0 +1,9 @@
+-- Content of the resources keyed by content identifier, which is the source
+-- the full-text index can be rebuilt from.
+CREATE TABLE IF NOT EXISTS contents (
+  cid Text NOT NULL,
+  content Text NOT NULL,
+
+  PRIMARY KEY (cid)
+)
+WITHOUT ROWID;
@@ -0,0 +1,4 @@
+-- Whether resource had content when ingested, so that resources ingested
+-- without one are not mistaken for ones whose content was lost. It is not
+-- known for the resources ingested before, which is left NULL.
+ALTER TABLE resources ADD COLUMN has_content Integer;
@@ -49,5 +49,3 @@ INNER JOIN
   resources
 ON
   reference_links.referrer_url = resources.url;
-
-PRAGMA user_version = 1;
\ No newline at end of file
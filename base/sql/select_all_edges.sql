@@ -0,0 +1,12 @@
+SELECT
+  referrer_url,
+  target_url,
+  kind,
+  name
+FROM
+  view_links
+ORDER BY
+  referrer_url,
+  target_url,
+  kind,
+  name;
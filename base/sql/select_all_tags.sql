@@ -0,0 +1,12 @@
+SELECT
+  name,
+  COUNT(DISTINCT target_url) AS count
+FROM
+  tags
+GROUP BY
+  name
+ORDER BY
+  count DESC,
+  name
+LIMIT
+  :limit;
@@ -0,0 +1,12 @@
+SELECT
+  url,
+  cid IN (SELECT cid FROM contents) AS content_stored
+FROM
+  resources
+WHERE
+  cid IS NOT NULL
+  -- Resources ingested without content have nothing to index, resources
+  -- ingested before that was recorded are assumed to have had content.
+  AND (resources.has_content IS NULL OR resources.has_content = 1)
+ORDER BY
+  url;
@@ -0,0 +1,46 @@
+-- Records are numbered by their position in the ordered list of links, so
+-- that only ones within the window are selected.
+SELECT * FROM (
+  SELECT
+    kind,
+    referrer_url,
+    referrer_cid,
+    referrer_title,
+    referrer_description,
+    referrer_icon,
+    referrer_image,
+    referrer_fragment,
+    referrer_location,
+
+    target_url,
+    identifier,
+    name,
+    title,
+
+    referrer_created_at,
+    referrer_modified_at,
+    referrer_ingested_at,
+    referrer_language,
+    ROW_NUMBER() OVER (ORDER BY :order) AS position,
+    COUNT(*) OVER () AS total_count
+  FROM
+    view_links
+  WHERE
+    -- Fragment of the target is not part of the resource URL.
+    NOT EXISTS (
+      SELECT 1
+      FROM resources
+      WHERE resources.url = CASE
+        WHEN instr(view_links.target_url, '#') > 0
+        THEN substr(view_links.target_url, 1, instr(view_links.target_url, '#') - 1)
+        ELSE view_links.target_url
+      END
+    )
+)
+WHERE
+  -- First record is always selected so that total count is known even if
+  -- none of the records are in the window.
+  position = 1
+  OR (position > :offset AND (:limit < 0 OR position <= :offset + :limit))
+ORDER BY
+  position;
@@ -0,0 +1,26 @@
+SELECT DISTINCT
+  referrer_url,
+  target_url,
+  kind,
+  name
+FROM (
+  SELECT
+    referrer_url,
+    -- Fragment of the target is not part of the resource URL.
+    CASE
+      WHEN instr(target_url, '#') > 0
+      THEN substr(target_url, 1, instr(target_url, '#') - 1)
+      ELSE target_url
+    END AS target_url,
+    kind,
+    name
+  FROM
+    view_links
+)
+WHERE
+  referrer_url IN (:keys)
+ORDER BY
+  referrer_url,
+  target_url,
+  kind,
+  name;
@@ -0,0 +1,19 @@
+SELECT
+  resources.url,
+  resources.cid,
+  resources.title,
+  resources.description,
+  resources.icon,
+  resources.image,
+  resources.created_at,
+  resources.modified_at,
+  resources.language,
+  contents.content
+FROM
+  resources
+LEFT JOIN
+  contents
+ON
+  resources.cid = contents.cid
+WHERE
+  resources.url = :url;
@@ -0,0 +1,26 @@
+SELECT
+  kind,
+  referrer_url,
+  referrer_cid,
+  referrer_title,
+  referrer_description,
+  referrer_icon,
+  referrer_image,
+  referrer_fragment,
+  referrer_location,
+  
+  target_url,
+  identifier,
+  name,
+  title,
+
+  referrer_created_at,
+  referrer_modified_at,
+  referrer_ingested_at,
+  referrer_language
+FROM
+  view_links
+WHERE
+  target_url LIKE 'file://%'
+ORDER BY
+  :order;
@@ -0,0 +1,13 @@
+SELECT
+  resources.url,
+  resources.title,
+  tags.name
+FROM
+  resources
+LEFT JOIN
+  tags
+ON
+  resources.url = tags.target_url
+ORDER BY
+  resources.url,
+  tags.name;
@@ -0,0 +1,13 @@
+SELECT
+  resources.url,
+  resources.title,
+  resources.language,
+  contents.content
+FROM
+  resources
+LEFT JOIN
+  contents
+ON
+  resources.cid = contents.cid
+WHERE
+  resources.url = :url;
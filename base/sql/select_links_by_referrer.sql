@@ -1,19 +1,37 @@
-SELECT
-  kind,
-  referrer_url,
-  referrer_cid,
-  referrer_title,
-  referrer_description,
-  referrer_icon,
-  referrer_image,
-  referrer_fragment,
-  referrer_location,
-  
-  target_url,
-  identifier,
-  name,
-  title
-FROM
-  view_links
+-- Records are numbered by their position in the ordered list of links for
+-- each key, so that only ones within the window are selected.
+SELECT * FROM (
+  SELECT
+    kind,
+    referrer_url,
+    referrer_cid,
+    referrer_title,
+    referrer_description,
+    referrer_icon,
+    referrer_image,
+    referrer_fragment,
+    referrer_location,
+
+    target_url,
+    identifier,
+    name,
+    title,
+
+    referrer_created_at,
+    referrer_modified_at,
+    referrer_ingested_at,
+    referrer_language,
+    ROW_NUMBER() OVER (PARTITION BY referrer_url ORDER BY :order) AS position,
+    COUNT(*) OVER (PARTITION BY referrer_url) AS total_count
+  FROM
+    view_links
+  WHERE
+    referrer_url IN (:keys)
+)
 WHERE
-  referrer_url = :referrer_url;
\ No newline at end of file
+  -- First record is always selected so that total count is known even if
+  -- none of the records are in the window.
+  position = 1
+  OR (position > :offset AND (:limit < 0 OR position <= :offset + :limit))
+ORDER BY
+  position;
@@ -0,0 +1,9 @@
+SELECT
+  name,
+  COUNT(DISTINCT target_url) AS count
+FROM
+  tags
+WHERE
+  instr(name, '/') > 0
+GROUP BY
+  name;
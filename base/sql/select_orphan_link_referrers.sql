@@ -0,0 +1,15 @@
+SELECT
+  referrer_url,
+  COUNT(*) AS count
+FROM
+  (
+    SELECT referrer_url FROM inline_links
+    UNION ALL
+    SELECT referrer_url FROM reference_links
+  )
+WHERE
+  referrer_url NOT IN (SELECT url FROM resources)
+GROUP BY
+  referrer_url
+ORDER BY
+  referrer_url;
@@ -0,0 +1,19 @@
+SELECT url, cid, title, description, icon, image, created_at, modified_at, ingested_at, language
+FROM resources
+WHERE
+  NOT EXISTS (SELECT 1 FROM view_links WHERE view_links.referrer_url = resources.url)
+  AND NOT EXISTS (SELECT 1 FROM view_links WHERE view_links.target_url = resources.url)
+  -- Links to fragments of the resource start with `url#`, which is matched as
+  -- a range (`$` follows `#`) so that it is exact and can use the index.
+  AND NOT EXISTS (
+    SELECT 1
+    FROM view_links
+    WHERE
+      view_links.target_url >= resources.url || '#'
+      AND view_links.target_url < resources.url || '$'
+  )
+ORDER BY
+  title COLLATE NOCASE,
+  url
+LIMIT
+  :limit;
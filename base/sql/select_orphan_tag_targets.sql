@@ -0,0 +1,11 @@
+SELECT
+  target_url,
+  COUNT(*) AS count
+FROM
+  tags
+WHERE
+  target_url NOT IN (SELECT url FROM resources)
+GROUP BY
+  target_url
+ORDER BY
+  target_url;
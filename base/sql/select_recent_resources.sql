@@ -0,0 +1,10 @@
+SELECT url, cid, title, description, icon, image, created_at, modified_at, ingested_at, language
+FROM resources
+WHERE
+  (:since IS NULL OR modified_at >= :since)
+  AND (:until IS NULL OR modified_at < :until)
+ORDER BY
+  modified_at DESC,
+  url
+LIMIT
+  :limit;
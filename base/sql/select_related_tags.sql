@@ -0,0 +1,19 @@
+SELECT
+  tag.name,
+  related.name,
+  COUNT(DISTINCT related.target_url) AS count
+FROM
+  tags AS tag
+INNER JOIN
+  tags AS related
+ON
+  related.target_url = tag.target_url
+  AND related.name != tag.name
+WHERE
+  tag.name IN (:keys)
+GROUP BY
+  tag.name,
+  related.name
+ORDER BY
+  count DESC,
+  related.name;
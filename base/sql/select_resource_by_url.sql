@@ -1,4 +0,0 @@
-SELECT cid, title, description, icon, image
-FROM resources
-WHERE url = :url
-LIMIT 1;
@@ -0,0 +1,3 @@
+SELECT created_at
+FROM resources
+WHERE url = :url;
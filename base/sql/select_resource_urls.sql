@@ -0,0 +1,3 @@
+SELECT url
+FROM resources
+ORDER BY url;
@@ -0,0 +1,4 @@
+SELECT url, cid, title, description, icon, image, created_at, modified_at, ingested_at, language
+FROM resources
+WHERE cid = :cid
+ORDER BY url;
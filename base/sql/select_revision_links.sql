@@ -0,0 +1,12 @@
+SELECT
+  version,
+  target_url,
+  name
+FROM
+  revision_links
+WHERE
+  url = :url
+  AND (:version IS NULL OR version = :version)
+ORDER BY
+  version,
+  target_url;
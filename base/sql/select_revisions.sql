@@ -0,0 +1,12 @@
+SELECT
+  version,
+  cid,
+  title,
+  description,
+  recorded_at
+FROM
+  revisions
+WHERE
+  url = :url
+ORDER BY
+  version;
@@ -0,0 +1,9 @@
+SELECT
+  name,
+  COUNT(DISTINCT target_url) AS count
+FROM
+  tags
+WHERE
+  name IN (:keys)
+GROUP BY
+  name;
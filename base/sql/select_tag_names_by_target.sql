@@ -0,0 +1,4 @@
+SELECT name
+FROM tags
+WHERE target_url = :target_url
+ORDER BY name;
@@ -1,9 +1,26 @@
-SELECT
-  target_url,
-  name,
-  target_fragment,
-  target_location
-FROM
-  tags
+-- Records are numbered by their position in the ordered list of tags for
+-- each key, so that only ones within the window are selected.
+SELECT * FROM (
+  SELECT
+    tags.target_url,
+    tags.name,
+    tags.target_fragment,
+    tags.target_location,
+    ROW_NUMBER() OVER (PARTITION BY tags.name ORDER BY :order) AS position,
+    COUNT(*) OVER (PARTITION BY tags.name) AS total_count
+  FROM
+    tags
+  LEFT JOIN
+    resources
+  ON
+    tags.target_url = resources.url
+  WHERE
+    tags.name IN (:keys)
+)
 WHERE
-  name = :name;
\ No newline at end of file
+  -- First record is always selected so that total count is known even if
+  -- none of the records are in the window.
+  position = 1
+  OR (position > :offset AND (:limit < 0 OR position <= :offset + :limit))
+ORDER BY
+  position;
@@ -0,0 +1,27 @@
+-- Records are numbered by their position in the ordered list of tags, so
+-- that only ones within the window are selected.
+SELECT * FROM (
+  SELECT
+    tags.target_url,
+    tags.name,
+    tags.target_fragment,
+    tags.target_location,
+    ROW_NUMBER() OVER (ORDER BY :order) AS position,
+    COUNT(*) OVER () AS total_count
+  FROM
+    tags
+  LEFT JOIN
+    resources
+  ON
+    tags.target_url = resources.url
+  WHERE
+    tags.name = :name
+    OR substr(tags.name, 1, length(:prefix)) = :prefix
+)
+WHERE
+  -- First record is always selected so that total count is known even if
+  -- none of the records are in the window.
+  position = 1
+  OR (position > :offset AND (:limit < 0 OR position <= :offset + :limit))
+ORDER BY
+  position;
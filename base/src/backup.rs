@@ -0,0 +1,138 @@
+use crate::config::Config;
+use crate::data::Snapshot;
+use crate::index;
+use crate::index::IndexService;
+use crate::store;
+use crate::store::DataStore;
+use chrono::Utc;
+use juniper::FieldResult;
+use log;
+use std::io;
+use std::path::Path;
+
+/// Takes a snapshot of the open knowledge base into the `dest` directory,
+/// which is laid out the same way as the data directory. Pending index
+/// changes are committed first.
+///
+/// Index writer is held while both the database and the index are copied.
+/// Resources are stored before they are indexed, so one ingested in the
+/// meantime may end up only in the database copy, which `check --repair`
+/// resolves by indexing it from the stored content.
+pub async fn snapshot(
+    store: &DataStore,
+    index: &IndexService,
+    dest: &Path,
+) -> FieldResult<Snapshot> {
+    let target = Config::from_data_dir(dest.to_path_buf());
+    prepare(&target)?;
+    log::info!("Taking snapshot into {:?}", dest);
+
+    let created_at = Utc::now();
+    index
+        .backup(&target.index_path(), || store.backup(&target.store_path()))
+        .await?;
+
+    log::info!("Snapshot was taken into {:?}", dest);
+    Ok(Snapshot {
+        path: format!("{}", dest.display()),
+        created_at,
+    })
+}
+
+/// Takes a snapshot of the knowledge base described by `config` into the
+/// `dest` directory. It reads files directly, so it is safe while server is
+/// running, but only changes committed to the index are included.
+pub fn backup(config: &Config, dest: &Path) -> io::Result<Snapshot> {
+    if !config.store_path().is_file() || !config.index_path().is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No knowledge base found at {:?}", config.data_dir()),
+        ));
+    }
+    let target = Config::from_data_dir(dest.to_path_buf());
+    prepare(&target)?;
+
+    let created_at = Utc::now();
+    store::backup(&config.store_path(), &target.store_path())?;
+    index::backup(&config.index_path(), &target.index_path())
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?;
+
+    Ok(Snapshot {
+        path: format!("{}", dest.display()),
+        created_at,
+    })
+}
+
+/// Replaces knowledge base described by `config` with the snapshot from the
+/// `source` directory. Fails if knowledge base is in use by a running server.
+pub fn restore(config: &Config, source: &Path) -> io::Result<()> {
+    let snapshot = Config::from_data_dir(source.to_path_buf());
+    if !snapshot.store_path().is_file() || !snapshot.index_path().is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No snapshot found at {:?}", source),
+        ));
+    }
+
+    std::fs::create_dir_all(config.data_dir())?;
+    // Index copy is staged first since it fails if server is running, in
+    // which case nothing is changed. Database is restored in a single
+    // transaction, and only once it is, staged index is swapped in. If that
+    // fails, database is rolled back from the copy taken before.
+    let index = index::restore(&config.index_path(), &snapshot.index_path())
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?;
+    let store_path = config.store_path();
+    let rollback = store_path.with_extension("rollback");
+    let existing = store_path.is_file();
+    if existing {
+        store::backup(&store_path, &rollback)?;
+    }
+    let result = store::restore(&store_path, &snapshot.store_path()).and_then(|_| {
+        index
+            .commit()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))
+    });
+    if result.is_err() {
+        log::warn!("Restore failed, rolling back database {:?}", store_path);
+        let rolled_back = if existing {
+            store::restore(&store_path, &rollback)
+        } else {
+            std::fs::remove_file(&store_path)
+        };
+        if let Err(error) = rolled_back {
+            log::error!("Failed to roll back database {:?}: {:}", store_path, error);
+        }
+    }
+    if existing {
+        std::fs::remove_file(&rollback)?;
+    }
+
+    result
+}
+
+/// Creates snapshot directory, refusing to overwrite existing snapshot.
+fn prepare(target: &Config) -> io::Result<()> {
+    if target.store_path().exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Snapshot already exists at {:?}", target.data_dir()),
+        ));
+    }
+    std::fs::create_dir_all(target.data_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_requires_knowledge_base() {
+        let root = std::env::temp_dir().join(format!("knowledge-backup-{:}", std::process::id()));
+        let config = Config::from_data_dir(root.join("missing"));
+        let dest = root.join("snapshot");
+
+        assert!(backup(&config, &dest).is_err());
+        assert!(!config.store_path().exists());
+        assert!(!dest.exists());
+    }
+}
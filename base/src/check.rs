@@ -0,0 +1,178 @@
+use crate::data::IndexableResource;
+use crate::index::IndexService;
+use crate::store::DataStore;
+use juniper::FieldResult;
+use log;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Discrepancies found between the SQLite store and the Tantivy index.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Indexed documents with no corresponding resource in the store.
+    pub orphan_documents: Vec<String>,
+    /// Resources that are indexed more than once.
+    pub duplicate_documents: Vec<String>,
+    /// Resources with content that are not indexed but can be, because their
+    /// content is stored.
+    pub unindexed_resources: Vec<String>,
+    /// Resources with content that are not indexed and can't be because their
+    /// content is not stored, they need to be ingested again.
+    pub missing_contents: Vec<String>,
+    /// Referrers that links are stored for, even though they are not stored,
+    /// along with the number of such links.
+    pub orphan_links: Vec<(String, usize)>,
+    /// Resources that tags are stored for, even though they are not stored,
+    /// along with the number of such tags.
+    pub orphan_tags: Vec<(String, usize)>,
+    /// Whether repairable discrepancies were repaired.
+    pub repaired: bool,
+}
+
+impl Report {
+    pub fn is_consistent(&self) -> bool {
+        self.orphan_documents.is_empty()
+            && self.duplicate_documents.is_empty()
+            && self.unindexed_resources.is_empty()
+            && self.missing_contents.is_empty()
+            && self.orphan_links.is_empty()
+            && self.orphan_tags.is_empty()
+    }
+}
+
+/// Walks both the store and the index and reports discrepancies between them.
+/// If `repair` is set, discrepancies are also fixed where possible:
+///
+/// - Orphan documents are removed from the index.
+/// - Duplicate documents and unindexed resources are indexed again from the
+///   stored content.
+/// - Orphan links and tags are deleted.
+///
+/// Resources with missing content can only be fixed by ingesting them again.
+pub async fn check(store: &DataStore, index: &IndexService, repair: bool) -> FieldResult<Report> {
+    let mut report = Report::default();
+
+    let resources: HashSet<String> = store.select_resource_urls()?.into_iter().collect();
+    let mut documents: HashMap<String, usize> = HashMap::new();
+    for url in index.indexed_urls()? {
+        *documents.entry(url).or_insert(0) += 1;
+    }
+    for (url, count) in documents.iter() {
+        if !resources.contains(url) {
+            report.orphan_documents.push(url.clone());
+        } else if *count > 1 {
+            report.duplicate_documents.push(url.clone());
+        }
+    }
+    report.orphan_documents.sort();
+    report.duplicate_documents.sort();
+
+    for (url, has_content) in store.select_content_resource_urls()? {
+        if !documents.contains_key(&url) {
+            if has_content {
+                report.unindexed_resources.push(url);
+            } else {
+                report.missing_contents.push(url);
+            }
+        }
+    }
+    report.orphan_links = store.select_orphan_links()?;
+    report.orphan_tags = store.select_orphan_tags()?;
+
+    if repair {
+        for url in report.orphan_documents.iter() {
+            log::info!("Removing orphan document {:} from index", url);
+            index.forget(url).await?;
+        }
+        let reindexed = report
+            .duplicate_documents
+            .iter()
+            .chain(report.unindexed_resources.iter());
+        for url in reindexed {
+            if let Some(IndexableResource {
+                url,
+                title,
+                language,
+                content: Some(content),
+                tags,
+            }) = store.select_indexable_resource(url)?
+            {
+                log::info!("Indexing {:} again", url);
+                let language = language.as_ref().map(String::as_str);
+                index
+                    .ingest(&url, &title, &content, language, &tags)
+                    .await?;
+            }
+        }
+        let deleted = store.delete_orphans()?;
+        log::info!("Deleted {:} orphan links and tags", deleted);
+        index.commit().await?;
+        report.repaired = true;
+    }
+
+    Ok(report)
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_consistent() {
+            return writeln!(f, "Store and index are consistent");
+        }
+        let status = if self.repaired { "repaired" } else { "found" };
+        write_urls(
+            f,
+            &format!("Orphan index documents {:}", status),
+            &self.orphan_documents,
+        )?;
+        write_urls(
+            f,
+            &format!("Duplicate index documents {:}", status),
+            &self.duplicate_documents,
+        )?;
+        write_urls(
+            f,
+            &format!("Unindexed resources {:}", status),
+            &self.unindexed_resources,
+        )?;
+        write_urls(
+            f,
+            "Unindexed resources without stored content (ingest them again)",
+            &self.missing_contents,
+        )?;
+        write_counts(
+            f,
+            &format!("Links of missing referrers {:}", status),
+            &self.orphan_links,
+        )?;
+        write_counts(
+            f,
+            &format!("Tags of missing resources {:}", status),
+            &self.orphan_tags,
+        )
+    }
+}
+
+fn write_urls(f: &mut fmt::Formatter<'_>, title: &str, urls: &[String]) -> fmt::Result {
+    if !urls.is_empty() {
+        writeln!(f, "{:}: {:}", title, urls.len())?;
+        for url in urls {
+            writeln!(f, "  {:}", url)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_counts(
+    f: &mut fmt::Formatter<'_>,
+    title: &str,
+    counts: &[(String, usize)],
+) -> fmt::Result {
+    if !counts.is_empty() {
+        let total: usize = counts.iter().map(|(_, count)| count).sum();
+        writeln!(f, "{:}: {:}", title, total)?;
+        for (url, count) in counts {
+            writeln!(f, "  {:} ({:})", url, count)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use dirs;
+use std::env;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Environment variable that can be used to override default data directory.
+pub const DATA_DIR_VAR: &str = "KNOWLEDGE_SERVER_DATA_DIR";
+/// Environment variable that can be used to select a profile.
+pub const PROFILE_VAR: &str = "KNOWLEDGE_SERVER_PROFILE";
+
+/// Describes where knowledge base keeps its data. Each profile lives in a
+/// separate directory and therefore has its own SQLite database and Tantivy
+/// index.
+#[derive(Debug, Clone)]
+pub struct Config {
+    data_dir: PathBuf,
+}
+
+impl Config {
+    /// Creates configuration from the given options, falling back to the
+    /// environment variables and then to the `~/.knowledge-service` default.
+    /// Named profiles are stored in the `profiles/$name` subdirectory of the
+    /// data directory.
+    pub fn new(data_dir: Option<PathBuf>, profile: Option<String>) -> io::Result<Self> {
+        let root = match data_dir.or_else(|| env::var_os(DATA_DIR_VAR).map(PathBuf::from)) {
+            Some(path) if path.is_absolute() => path,
+            Some(path) => env::current_dir()?.join(path),
+            None => Config::default_data_dir()?,
+        };
+
+        let profile = profile
+            .or_else(|| env::var(PROFILE_VAR).ok())
+            .filter(|name| !name.is_empty());
+
+        let data_dir = match profile {
+            Some(name) => root
+                .join("profiles")
+                .join(Config::subdirectory("profile", &name)?),
+            None => root,
+        };
+
+        Ok(Config { data_dir })
+    }
+    /// Creates configuration for the knowledge base stored in the given
+    /// directory, ignoring the environment variables.
+    pub fn from_data_dir(data_dir: PathBuf) -> Self {
+        Config { data_dir }
+    }
+    pub fn default_data_dir() -> io::Result<PathBuf> {
+        let mut path = dirs::home_dir().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Unable to locate user home directory",
+            )
+        })?;
+        path.push(".knowledge-service");
+        Ok(path)
+    }
+    /// Validates that `name` of the `kind` directory is a single path
+    /// component, so that it can not point outside of its parent directory.
+    fn subdirectory<'a>(kind: &str, name: &'a str) -> io::Result<&'a Path> {
+        let path = Path::new(name);
+        let mut components = path.components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => Ok(path),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid {:} name {:?}", kind, name),
+            )),
+        }
+    }
+
+    /// Directory all the data is stored in.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+    /// Path to the SQLite database.
+    pub fn store_path(&self) -> PathBuf {
+        self.data_dir.join("knowledge.sqlite")
+    }
+    /// Path to the Tantivy index directory.
+    pub fn index_path(&self) -> PathBuf {
+        self.data_dir.join("tantivy")
+    }
+    /// Path to the directory of the snapshot with a given name, taken through
+    /// the `snapshot` GraphQL mutation.
+    pub fn snapshot_path(&self, name: &str) -> io::Result<PathBuf> {
+        Ok(self
+            .data_dir
+            .join("snapshots")
+            .join(Config::subdirectory("snapshot", name)?))
+    }
+    /// Path to the log file written by the daemon.
+    pub fn log_path(&self) -> PathBuf {
+        self.data_dir.join("service.log")
+    }
+}
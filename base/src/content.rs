@@ -0,0 +1,38 @@
+use multibase::Base;
+use multihash::Sha2_256;
+
+/// CID version prefix, see https://github.com/multiformats/cid
+const CID_V1: u8 = 0x01;
+/// Multicodec code for the raw binary content.
+const RAW: u8 = 0x55;
+
+/// Computes content identifier of the given content. It is a CIDv1 of the raw
+/// content bytes with a sha2-256 multihash, encoded in base32 like
+/// `bafkrei...` which is the same as IPFS would produce for a single block.
+pub fn cid(content: &[u8]) -> String {
+    let hash = Sha2_256::digest(content);
+    // Both codes are under 0x80 so their varint encoding is a single byte.
+    let mut bytes = vec![CID_V1, RAW];
+    bytes.extend_from_slice(hash.as_bytes());
+    multibase::encode(Base::Base32Lower, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cid_matches_ipfs() {
+        // Same as `ipfs add --cid-version 1 --raw-leaves` of the same bytes.
+        assert_eq!(
+            cid(b"hello world"),
+            "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"
+        );
+    }
+
+    #[test]
+    fn cid_is_stable() {
+        assert_eq!(cid(b"# Notes\n"), cid(b"# Notes\n"));
+        assert_ne!(cid(b"# Notes\n"), cid(b"# Notes"));
+    }
+}
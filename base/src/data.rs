@@ -1,13 +1,49 @@
+use chrono::{DateTime, Utc};
 use juniper;
+use serde::{Deserialize, Serialize};
 use std::convert::From;
 use tique::topterms::Keywords;
 
-#[derive(juniper::GraphQLEnum, Clone, Copy, Debug)]
+#[derive(juniper::GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum LinkKind {
     Inline = 0,
     Reference = 1,
 }
 
+/// Direction in which links are followed when traversing the link graph.
+#[derive(juniper::GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkDirection {
+    /// From the referrer to the link target.
+    Outgoing,
+    /// From the link target to the referrer.
+    Incoming,
+    /// Both ways.
+    Both,
+}
+
+/// Order in which links are listed.
+#[derive(juniper::GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LinkOrder {
+    /// Alphabetically by the URL of the link target.
+    TargetUrl,
+    /// Alphabetically by the title of the referrer resource.
+    ReferrerTitle,
+    /// Most recently ingested referrers first.
+    IngestTime,
+}
+
+/// Order in which tags are listed.
+#[derive(juniper::GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TagOrder {
+    /// Alphabetically by the tag name.
+    Name,
+    /// Alphabetically by the title of the tagged resource.
+    TargetTitle,
+    /// Most recently ingested tagged resources first.
+    IngestTime,
+}
+
 #[derive(Clone, Debug)]
 pub struct Link {
     pub kind: LinkKind,
@@ -17,6 +53,10 @@ pub struct Link {
     pub referrer_description: String,
     pub referrer_icon: Option<String>,
     pub referrer_image: Option<String>,
+    pub referrer_created_at: Option<DateTime<Utc>>,
+    pub referrer_modified_at: Option<DateTime<Utc>>,
+    pub referrer_ingested_at: Option<DateTime<Utc>>,
+    pub referrer_language: Option<String>,
 
     pub referrer_fragment: Option<String>,
     pub referrer_location: Option<String>,
@@ -35,6 +75,94 @@ pub struct Tag {
     pub target_location: Option<String>,
 }
 
+/// Information about the page of the connection as described by the Relay
+/// [cursor connections specification][].
+/// [cursor connections specification]:https://relay.dev/graphql/connections.htm
+#[derive(juniper::GraphQLObject, Clone, Debug)]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub has_previous_page: bool,
+    pub start_cursor: Option<String>,
+    pub end_cursor: Option<String>,
+}
+
+/// Slice of the larger list of nodes along with the cursors identifying
+/// position of each node in that list.
+#[derive(Clone, Debug)]
+pub struct Page<T> {
+    pub edges: Vec<(String, T)>,
+    pub page_info: PageInfo,
+    pub total_count: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct LinkEdge {
+    pub cursor: String,
+    pub node: Link,
+}
+
+#[derive(Clone, Debug)]
+pub struct LinkConnection {
+    pub edges: Vec<LinkEdge>,
+    pub page_info: PageInfo,
+    pub total_count: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct TagEdge {
+    pub cursor: String,
+    pub node: Tag,
+}
+
+#[derive(Clone, Debug)]
+pub struct TagConnection {
+    pub edges: Vec<TagEdge>,
+    pub page_info: PageInfo,
+    pub total_count: usize,
+}
+
+/// Link as recorded in the resource revision.
+#[derive(juniper::GraphQLObject, Clone, Debug, PartialEq, Eq)]
+pub struct RevisionLink {
+    #[graphql(name = "targetURL")]
+    pub target_url: String,
+    pub name: String,
+}
+
+/// Snapshot of the resource, recorded whenever it is ingested with changes.
+#[derive(juniper::GraphQLObject, Clone, Debug)]
+pub struct Revision {
+    /// Sequential number of the revision, starting with `1`.
+    pub version: i32,
+    pub cid: Option<String>,
+    pub title: String,
+    pub description: String,
+    /// Time revision was recorded.
+    pub recorded_at: DateTime<Utc>,
+    /// Links resource had in this revision.
+    pub links: Vec<RevisionLink>,
+    /// Links that were not present in the previous revision.
+    pub added_links: Vec<RevisionLink>,
+    /// Links of the previous revision that are no longer present.
+    pub removed_links: Vec<RevisionLink>,
+}
+
+/// Distinct tag name along with the number of resources it is applied to.
+#[derive(Clone, Debug)]
+pub struct TagInfo {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Separates segments of hierarchical tags e.g. `project/ksp/design`.
+pub const TAG_SEPARATOR: char = '/';
+
+/// Returns name of the parent tag in the hierarchy, e.g. `project/ksp` for the
+/// `project/ksp/design` tag, or `None` for the top level tags.
+pub fn parent_tag_name(name: &str) -> Option<&str> {
+    name.rfind(TAG_SEPARATOR).map(|index| &name[..index])
+}
+
 #[derive(Debug, Clone)]
 pub struct Resource {
     pub url: String,
@@ -48,6 +176,14 @@ pub struct ResourceInfo {
     pub cid: Option<String>,
     pub icon: Option<String>,
     pub image: Option<String>,
+    /// Time resource was first seen.
+    pub created_at: Option<DateTime<Utc>>,
+    /// Time resource was last modified.
+    pub modified_at: Option<DateTime<Utc>>,
+    /// Time resource was last ingested into the knowledge base.
+    pub ingested_at: Option<DateTime<Utc>>,
+    /// ISO 639-1 code of the resource language, e.g. `en`.
+    pub language: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -57,16 +193,168 @@ pub struct SimilarResource {
     pub target_url: String,
 }
 
+/// Resource matching the full-text search query.
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub score: f32,
+    pub url: String,
+    /// HTML fragment of the content with matched terms highlighted.
+    pub snippet: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchEdge {
+    pub cursor: String,
+    pub node: SearchResult,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchConnection {
+    pub edges: Vec<SearchEdge>,
+    pub page_info: PageInfo,
+    pub total_count: usize,
+    pub facets: Facets,
+}
+
+/// Restricts search and similarity results.
+#[derive(juniper::GraphQLInputObject, Clone, Debug, Default)]
+pub struct InputFilter {
+    /// Only include resources tagged with all of these tags (or tags nested
+    /// under them).
+    pub tags: Option<Vec<String>>,
+    /// Exclude resources tagged with any of these tags (or tags nested under
+    /// them).
+    pub exclude_tags: Option<Vec<String>>,
+    /// Only include resources with URLs under this prefix, e.g.
+    /// `file:///Users/me/notes/project`. Prefix is matched by whole path
+    /// segments.
+    #[graphql(name = "urlPrefix")]
+    pub url_prefix: Option<String>,
+}
+
+/// Number of matching resources in a facet.
+#[derive(juniper::GraphQLObject, Clone, Debug)]
+pub struct FacetCount {
+    pub name: String,
+    pub count: i32,
+}
+
+/// Breakdown of the matching resources by tags and URLs.
+#[derive(juniper::GraphQLObject, Clone, Debug, Default)]
+pub struct Facets {
+    /// Counts of top level tags, and of tags nested directly under the tags
+    /// in the filter.
+    pub tags: Vec<FacetCount>,
+    /// Counts of URLs one path segment deeper than the `urlPrefix` of the
+    /// filter, or than the root if there is no prefix.
+    pub urls: Vec<FacetCount>,
+}
+
+/// Resource as stored, with everything needed to index it.
+#[derive(Clone, Debug)]
+pub struct IndexableResource {
+    pub url: String,
+    pub title: String,
+    pub language: Option<String>,
+    /// Content is `None` for resources ingested before contents were stored.
+    pub content: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// Link between two resources of the link graph. All the links of the same
+/// kind and name between the same resources are represented by a single edge.
+#[derive(juniper::GraphQLObject, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GraphEdge {
+    #[graphql(name = "sourceURL")]
+    pub source_url: String,
+    #[graphql(name = "targetURL")]
+    pub target_url: String,
+    pub kind: LinkKind,
+    pub name: String,
+}
+
+/// Resource in the link graph.
+#[derive(Clone, Debug)]
+pub struct GraphNode {
+    pub url: String,
+    /// Number of hops from the resource graph was built from.
+    pub depth: usize,
+}
+
+/// Resource as exported along with the link graph.
+#[derive(Clone, Debug)]
+pub struct GraphResource {
+    pub url: String,
+    /// Title of the resource, `None` if resource was never ingested.
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// Part of the link graph.
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Consistent copy of the knowledge base.
+#[derive(juniper::GraphQLObject, Clone, Debug)]
+pub struct Snapshot {
+    /// Directory snapshot was written to.
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outcome of rebuilding the full-text index.
+#[derive(juniper::GraphQLObject, Clone, Debug)]
+pub struct ReindexReport {
+    /// Number of resources that were indexed.
+    pub indexed: i32,
+    /// Number of resources that were skipped because their content was not
+    /// stored, these need to be ingested again.
+    pub skipped: i32,
+    /// URLs of the skipped resources. Their index entries, if any, are kept.
+    pub skipped_urls: Vec<String>,
+}
+
+/// Relative weights of the matches in different fields when scoring
+/// similarity of the resources. Title and tag matches are opt-in, so they
+/// have zero weight by default.
+#[derive(Clone, Copy, Debug)]
+pub struct SimilarityWeights {
+    /// Weight of keywords found in the content.
+    pub body: f32,
+    /// Weight of keywords found in the title.
+    pub title: f32,
+    /// Weight of the tags shared with the source.
+    pub tags: f32,
+}
+
+impl Default for SimilarityWeights {
+    fn default() -> Self {
+        SimilarityWeights {
+            body: 1.0,
+            title: 0.0,
+            tags: 0.0,
+        }
+    }
+}
+
 // TODO: Implement Debug
 #[derive(Clone)]
 pub struct SimilarResources {
     pub keywords: Keywords,
     pub source_url: String,
+    pub tags: Vec<String>,
+    pub weights: SimilarityWeights,
+    pub filter: InputFilter,
 }
 
-#[derive(juniper::GraphQLInputObject, Clone, Debug)]
+#[derive(juniper::GraphQLInputObject, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InputLink {
     #[graphql(name = "targetURL")]
+    #[serde(rename = "targetURL")]
     pub target_url: String,
 
     pub referrer_fragment: Option<String>,
@@ -78,14 +366,18 @@ pub struct InputLink {
     pub identifier: Option<String>,
 }
 
-#[derive(juniper::GraphQLInputObject, Clone, Debug)]
+#[derive(juniper::GraphQLInputObject, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InputTag {
     pub name: String,
     pub target_fragment: Option<String>,
     pub target_location: Option<String>,
 }
 
-#[derive(juniper::GraphQLInputObject, Clone, Debug)]
+/// Resource as submitted for ingestion. It is also the shape of the records
+/// in the NDJSON exports of the knowledge base.
+#[derive(juniper::GraphQLInputObject, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InputResource {
     pub url: String,
     pub cid: Option<String>,
@@ -95,6 +387,15 @@ pub struct InputResource {
     pub tags: Option<Vec<InputTag>>,
     pub icon: Option<String>,
     pub image: Option<String>,
+    /// Time resource was created, e.g. file creation time. Defaults to the
+    /// time resource is first ingested.
+    pub created_at: Option<DateTime<Utc>>,
+    /// Time resource was last modified, e.g. file modification time. Defaults
+    /// to the time resource is ingested.
+    pub modified_at: Option<DateTime<Utc>>,
+    /// ISO 639-1 code of the resource language, e.g. `en`. Detected from the
+    /// content when omitted.
+    pub language: Option<String>,
 
     pub content: Option<String>,
 }
@@ -110,6 +411,9 @@ pub struct Open {
 pub struct InputSimilar {
     pub url: Option<String>,
     pub content: String,
+    /// Tags of the source, defaults to tags the resource at `url` was
+    /// indexed with.
+    pub tags: Option<Vec<String>>,
 }
 
 impl From<String> for Resource {
@@ -148,6 +452,10 @@ impl From<InputResource> for Resource {
                 description: input.description,
                 icon: input.icon,
                 image: input.image,
+                created_at: input.created_at,
+                modified_at: input.modified_at,
+                ingested_at: None,
+                language: input.language,
             }),
         }
     }
@@ -163,6 +471,10 @@ impl From<&InputResource> for Resource {
                 description: input.description.clone(),
                 icon: input.icon.clone(),
                 image: input.image.clone(),
+                created_at: input.created_at,
+                modified_at: input.modified_at,
+                ingested_at: None,
+                language: input.language.clone(),
             }),
         }
     }
@@ -178,6 +490,49 @@ impl From<String> for InputTag {
     }
 }
 
+impl From<Page<Link>> for LinkConnection {
+    fn from(page: Page<Link>) -> Self {
+        LinkConnection {
+            edges: page
+                .edges
+                .into_iter()
+                .map(|(cursor, node)| LinkEdge { cursor, node })
+                .collect(),
+            page_info: page.page_info,
+            total_count: page.total_count,
+        }
+    }
+}
+
+impl From<Page<Tag>> for TagConnection {
+    fn from(page: Page<Tag>) -> Self {
+        TagConnection {
+            edges: page
+                .edges
+                .into_iter()
+                .map(|(cursor, node)| TagEdge { cursor, node })
+                .collect(),
+            page_info: page.page_info,
+            total_count: page.total_count,
+        }
+    }
+}
+
+impl From<Page<SearchResult>> for SearchConnection {
+    fn from(page: Page<SearchResult>) -> Self {
+        SearchConnection {
+            edges: page
+                .edges
+                .into_iter()
+                .map(|(cursor, node)| SearchEdge { cursor, node })
+                .collect(),
+            page_info: page.page_info,
+            total_count: page.total_count,
+            facets: Facets::default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Query;
 #[derive(Debug, Clone)]
@@ -0,0 +1,594 @@
+use crate::data::{Graph, GraphEdge, GraphNode, GraphResource, LinkDirection, LinkKind};
+use crate::store::DataStore;
+use juniper::FieldResult;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Maximum number of hops neighborhood can span, since number of resources
+/// within reach grows quickly with each one.
+pub const MAX_DEPTH: usize = 5;
+
+/// Returns resources within `depth` hops from the resource with the given
+/// `url` along with all the links between them. Only links of the given
+/// `kinds` (all if empty) are followed in the given `direction`.
+pub fn neighborhood(
+    store: &DataStore,
+    url: &str,
+    depth: usize,
+    direction: LinkDirection,
+    kinds: &[LinkKind],
+) -> FieldResult<Graph> {
+    let depth = depth.min(MAX_DEPTH);
+    let mut graph = Graph::default();
+    let mut visited = HashSet::new();
+    let mut seen_edges = HashSet::new();
+
+    visited.insert(url.to_string());
+    graph.nodes.push(GraphNode {
+        url: url.to_string(),
+        depth: 0,
+    });
+
+    let mut frontier = vec![url.to_string()];
+    // One more hop than requested is made to collect the links between the
+    // resources at the edge of the neighborhood, but no resources are added.
+    for hop in 1..=depth + 1 {
+        if frontier.is_empty() {
+            break;
+        }
+        let mut next = vec![];
+        for (neighbor, edge) in adjacent_edges(store, &frontier, direction, kinds)? {
+            if !visited.contains(&neighbor) {
+                if hop > depth {
+                    continue;
+                }
+                visited.insert(neighbor.clone());
+                graph.nodes.push(GraphNode {
+                    url: neighbor.clone(),
+                    depth: hop,
+                });
+                next.push(neighbor);
+            }
+            if seen_edges.insert(edge.clone()) {
+                graph.edges.push(edge);
+            }
+        }
+        frontier = next;
+    }
+
+    Ok(graph)
+}
+
+/// Returns shortest path from the resource at `from` URL to the one at `to`
+/// URL that is at most `max_length` links long, or `None` if there is no such
+/// path. Only links of the given `kinds` (all if empty) are followed in the
+/// given `direction`.
+pub fn path(
+    store: &DataStore,
+    from: &str,
+    to: &str,
+    max_length: usize,
+    direction: LinkDirection,
+    kinds: &[LinkKind],
+) -> FieldResult<Option<Graph>> {
+    // Maps reached resources to the edge they were reached through.
+    let mut parents: HashMap<String, Option<GraphEdge>> = HashMap::new();
+    parents.insert(from.to_string(), None);
+
+    let mut frontier = vec![from.to_string()];
+    let mut length = 0;
+    while !parents.contains_key(to) {
+        if frontier.is_empty() || length == max_length {
+            return Ok(None);
+        }
+        length += 1;
+        let mut next = vec![];
+        for (neighbor, edge) in adjacent_edges(store, &frontier, direction, kinds)? {
+            if !parents.contains_key(&neighbor) {
+                parents.insert(neighbor.clone(), Some(edge));
+                next.push(neighbor);
+            }
+        }
+        frontier = next;
+    }
+
+    // Walk back from the destination to reconstruct the path.
+    let mut urls = vec![to.to_string()];
+    let mut edges = vec![];
+    let mut current = to.to_string();
+    while let Some(Some(edge)) = parents.get(&current) {
+        current = if edge.target_url == current {
+            edge.source_url.clone()
+        } else {
+            edge.target_url.clone()
+        };
+        urls.push(current.clone());
+        edges.push(edge.clone());
+    }
+    urls.reverse();
+    edges.reverse();
+
+    Ok(Some(Graph {
+        nodes: urls
+            .into_iter()
+            .enumerate()
+            .map(|(depth, url)| GraphNode { url, depth })
+            .collect(),
+        edges,
+    }))
+}
+
+/// Returns links of the given `kinds` (all if empty) from / to the resources
+/// with given `urls` depending on the `direction`, paired with the URL of the
+/// resource on the other end.
+fn adjacent_edges(
+    store: &DataStore,
+    urls: &[String],
+    direction: LinkDirection,
+    kinds: &[LinkKind],
+) -> FieldResult<Vec<(String, GraphEdge)>> {
+    let mut adjacent = vec![];
+    if direction != LinkDirection::Incoming {
+        for edge in store.select_edges_by_referrer(urls)? {
+            adjacent.push((edge.target_url.clone(), edge));
+        }
+    }
+    if direction != LinkDirection::Outgoing {
+        for edge in store.select_edges_by_target(urls)? {
+            adjacent.push((edge.source_url.clone(), edge));
+        }
+    }
+
+    Ok(adjacent
+        .into_iter()
+        .filter(|(_, edge)| kinds.is_empty() || kinds.contains(&edge.kind))
+        .collect())
+}
+
+/// Formats the whole link graph can be exported in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Graphviz DOT language.
+    Dot,
+    /// Graph Exchange XML Format used by Gephi.
+    Gexf,
+    GraphML,
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Dot => "text/vnd.graphviz;charset=utf-8",
+            Format::Gexf => "application/gexf+xml;charset=utf-8",
+            Format::GraphML => "application/graphml+xml;charset=utf-8",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = io::Error;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "dot" => Ok(Format::Dot),
+            "gexf" => Ok(Format::Gexf),
+            "graphml" => Ok(Format::GraphML),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unknown graph format {:?}, expected dot, gexf or graphml",
+                    name
+                ),
+            )),
+        }
+    }
+}
+
+/// Writes the whole link graph in the given `format`, with resources as nodes
+/// and links as edges. Links to fragments of a resource point to the resource
+/// itself. Link targets that were never ingested are included as nodes
+/// without a title.
+pub fn export<W: Write>(store: &DataStore, format: Format, writer: &mut W) -> FieldResult<()> {
+    let mut resources = store.select_graph_resources()?;
+    let edges: Vec<GraphEdge> = store
+        .select_all_edges()?
+        .into_iter()
+        .map(|edge| GraphEdge {
+            target_url: resource_url(&edge.target_url).to_string(),
+            ..edge
+        })
+        .collect();
+
+    let known: HashSet<String> = resources.iter().map(|node| node.url.clone()).collect();
+    let mut missing: Vec<&String> = edges
+        .iter()
+        .map(|edge| &edge.target_url)
+        .filter(|url| !known.contains(*url))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    missing.sort();
+    resources.extend(missing.into_iter().map(|url| GraphResource {
+        url: url.clone(),
+        title: None,
+        tags: vec![],
+    }));
+
+    match format {
+        Format::Dot => write_dot(writer, &resources, &edges)?,
+        Format::Gexf => write_gexf(writer, &resources, &edges)?,
+        Format::GraphML => write_graphml(writer, &resources, &edges)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_dot<W: Write>(
+    writer: &mut W,
+    nodes: &[GraphResource],
+    edges: &[GraphEdge],
+) -> io::Result<()> {
+    writeln!(writer, "digraph knowledge {{")?;
+    for node in nodes {
+        writeln!(
+            writer,
+            "  \"{}\" [label=\"{}\", title=\"{}\", tags=\"{}\"];",
+            escape_dot(&node.url),
+            escape_dot(label(node)),
+            escape_dot(node.title.as_ref().map(String::as_str).unwrap_or("")),
+            escape_dot(&node.tags.join(","))
+        )?;
+    }
+    for edge in edges {
+        writeln!(
+            writer,
+            "  \"{}\" -> \"{}\" [kind=\"{}\", name=\"{}\"];",
+            escape_dot(&edge.source_url),
+            escape_dot(&edge.target_url),
+            kind_name(edge.kind),
+            escape_dot(&edge.name)
+        )?;
+    }
+    writeln!(writer, "}}")
+}
+
+fn write_gexf<W: Write>(
+    writer: &mut W,
+    nodes: &[GraphResource],
+    edges: &[GraphEdge],
+) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<gexf xmlns="http://www.gexf.net/1.2draft" version="1.2">"#
+    )?;
+    writeln!(
+        writer,
+        r#"  <graph mode="static" defaultedgetype="directed">"#
+    )?;
+    writeln!(writer, r#"    <attributes class="node">"#)?;
+    writeln!(
+        writer,
+        r#"      <attribute id="title" title="title" type="string"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"      <attribute id="tags" title="tags" type="string"/>"#
+    )?;
+    writeln!(writer, r#"    </attributes>"#)?;
+    writeln!(writer, r#"    <attributes class="edge">"#)?;
+    writeln!(
+        writer,
+        r#"      <attribute id="kind" title="kind" type="string"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"      <attribute id="name" title="name" type="string"/>"#
+    )?;
+    writeln!(writer, r#"    </attributes>"#)?;
+
+    writeln!(writer, r#"    <nodes>"#)?;
+    for node in nodes {
+        writeln!(
+            writer,
+            r#"      <node id="{}" label="{}">"#,
+            escape_xml(&node.url),
+            escape_xml(label(node))
+        )?;
+        writeln!(writer, r#"        <attvalues>"#)?;
+        writeln!(
+            writer,
+            r#"          <attvalue for="title" value="{}"/>"#,
+            escape_xml(node.title.as_ref().map(String::as_str).unwrap_or(""))
+        )?;
+        writeln!(
+            writer,
+            r#"          <attvalue for="tags" value="{}"/>"#,
+            escape_xml(&node.tags.join(","))
+        )?;
+        writeln!(writer, r#"        </attvalues>"#)?;
+        writeln!(writer, r#"      </node>"#)?;
+    }
+    writeln!(writer, r#"    </nodes>"#)?;
+
+    writeln!(writer, r#"    <edges>"#)?;
+    for (n, edge) in edges.iter().enumerate() {
+        writeln!(
+            writer,
+            r#"      <edge id="{}" source="{}" target="{}">"#,
+            n,
+            escape_xml(&edge.source_url),
+            escape_xml(&edge.target_url)
+        )?;
+        writeln!(writer, r#"        <attvalues>"#)?;
+        writeln!(
+            writer,
+            r#"          <attvalue for="kind" value="{}"/>"#,
+            kind_name(edge.kind)
+        )?;
+        writeln!(
+            writer,
+            r#"          <attvalue for="name" value="{}"/>"#,
+            escape_xml(&edge.name)
+        )?;
+        writeln!(writer, r#"        </attvalues>"#)?;
+        writeln!(writer, r#"      </edge>"#)?;
+    }
+    writeln!(writer, r#"    </edges>"#)?;
+    writeln!(writer, r#"  </graph>"#)?;
+    writeln!(writer, r#"</gexf>"#)
+}
+
+fn write_graphml<W: Write>(
+    writer: &mut W,
+    nodes: &[GraphResource],
+    edges: &[GraphEdge],
+) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    writeln!(
+        writer,
+        r#"  <key id="title" for="node" attr.name="title" attr.type="string"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"  <key id="tags" for="node" attr.name="tags" attr.type="string"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"  <key id="kind" for="edge" attr.name="kind" attr.type="string"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"  <key id="name" for="edge" attr.name="name" attr.type="string"/>"#
+    )?;
+    writeln!(writer, r#"  <graph id="knowledge" edgedefault="directed">"#)?;
+    for node in nodes {
+        writeln!(writer, r#"    <node id="{}">"#, escape_xml(&node.url))?;
+        if let Some(title) = &node.title {
+            writeln!(
+                writer,
+                r#"      <data key="title">{}</data>"#,
+                escape_xml(title)
+            )?;
+        }
+        writeln!(
+            writer,
+            r#"      <data key="tags">{}</data>"#,
+            escape_xml(&node.tags.join(","))
+        )?;
+        writeln!(writer, r#"    </node>"#)?;
+    }
+    for (n, edge) in edges.iter().enumerate() {
+        writeln!(
+            writer,
+            r#"    <edge id="e{}" source="{}" target="{}">"#,
+            n,
+            escape_xml(&edge.source_url),
+            escape_xml(&edge.target_url)
+        )?;
+        writeln!(
+            writer,
+            r#"      <data key="kind">{}</data>"#,
+            kind_name(edge.kind)
+        )?;
+        writeln!(
+            writer,
+            r#"      <data key="name">{}</data>"#,
+            escape_xml(&edge.name)
+        )?;
+        writeln!(writer, r#"    </edge>"#)?;
+    }
+    writeln!(writer, r#"  </graph>"#)?;
+    writeln!(writer, r#"</graphml>"#)
+}
+
+/// URL of the resource link target belongs to, which is the target without
+/// the fragment, as fragments are not part of the resource URLs.
+fn resource_url(url: &str) -> &str {
+    match url.find('#') {
+        Some(index) => &url[..index],
+        None => url,
+    }
+}
+
+/// Node label, which is the title or the URL if resource has no title.
+fn label(node: &GraphResource) -> &str {
+    match &node.title {
+        Some(title) if !title.is_empty() => title,
+        _ => &node.url,
+    }
+}
+
+fn kind_name(kind: LinkKind) -> &'static str {
+    match kind {
+        LinkKind::Inline => "inline",
+        LinkKind::Reference => "reference",
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Escapes markup characters and replaces characters that are not allowed in
+/// XML 1.0 documents (most of the control characters) with U+FFFD.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push('\u{fffd}'),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::resource;
+
+    /// Store with `a -> b -> c -> d` chain and `e -> a` link.
+    fn chain() -> DataStore {
+        let store = DataStore::in_memory().unwrap();
+        store.ingest(&resource("a", &["b"], &[])).unwrap();
+        store.ingest(&resource("b", &["c"], &[])).unwrap();
+        store.ingest(&resource("c", &["d"], &[])).unwrap();
+        store.ingest(&resource("d", &[], &[])).unwrap();
+        store.ingest(&resource("e", &["a"], &[])).unwrap();
+        store
+    }
+
+    fn nodes(graph: &Graph) -> Vec<(&str, usize)> {
+        graph
+            .nodes
+            .iter()
+            .map(|node| (node.url.as_str(), node.depth))
+            .collect()
+    }
+
+    fn edges(graph: &Graph) -> Vec<(&str, &str)> {
+        graph
+            .edges
+            .iter()
+            .map(|edge| (edge.source_url.as_str(), edge.target_url.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn neighborhood_follows_direction() {
+        let store = chain();
+
+        let graph = neighborhood(&store, "a", 1, LinkDirection::Outgoing, &[]).unwrap();
+        assert_eq!(nodes(&graph), vec![("a", 0), ("b", 1)]);
+        assert_eq!(edges(&graph), vec![("a", "b")]);
+
+        let graph = neighborhood(&store, "a", 1, LinkDirection::Both, &[]).unwrap();
+        assert_eq!(nodes(&graph), vec![("a", 0), ("b", 1), ("e", 1)]);
+        assert_eq!(edges(&graph), vec![("a", "b"), ("e", "a")]);
+
+        let graph = neighborhood(&store, "a", 2, LinkDirection::Incoming, &[]).unwrap();
+        assert_eq!(nodes(&graph), vec![("a", 0), ("e", 1)]);
+
+        let graph = neighborhood(
+            &store,
+            "a",
+            2,
+            LinkDirection::Outgoing,
+            &[LinkKind::Reference],
+        )
+        .unwrap();
+        assert_eq!(nodes(&graph), vec![("a", 0)]);
+        assert!(graph.edges.is_empty());
+    }
+
+    #[test]
+    fn path_is_shortest() {
+        let store = chain();
+
+        let graph = path(&store, "a", "d", 5, LinkDirection::Outgoing, &[])
+            .unwrap()
+            .unwrap();
+        assert_eq!(nodes(&graph), vec![("a", 0), ("b", 1), ("c", 2), ("d", 3)]);
+        assert_eq!(edges(&graph), vec![("a", "b"), ("b", "c"), ("c", "d")]);
+
+        let graph = path(&store, "d", "e", 5, LinkDirection::Both, &[])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            nodes(&graph),
+            vec![("d", 0), ("c", 1), ("b", 2), ("a", 3), ("e", 4)]
+        );
+
+        assert!(path(&store, "d", "a", 5, LinkDirection::Outgoing, &[])
+            .unwrap()
+            .is_none());
+        assert!(path(&store, "a", "d", 2, LinkDirection::Outgoing, &[])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn traverses_fragment_links_to_resource() {
+        let store = DataStore::in_memory().unwrap();
+        store.ingest(&resource("a", &["b#intro"], &[])).unwrap();
+        store.ingest(&resource("b", &["c"], &[])).unwrap();
+        store.ingest(&resource("c", &[], &[])).unwrap();
+
+        let graph = path(&store, "a", "c", 5, LinkDirection::Outgoing, &[])
+            .unwrap()
+            .unwrap();
+        assert_eq!(nodes(&graph), vec![("a", 0), ("b", 1), ("c", 2)]);
+        assert_eq!(edges(&graph), vec![("a", "b"), ("b", "c")]);
+
+        let graph = neighborhood(&store, "b", 1, LinkDirection::Incoming, &[]).unwrap();
+        assert_eq!(nodes(&graph), vec![("b", 0), ("a", 1)]);
+        assert_eq!(edges(&graph), vec![("a", "b")]);
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(
+            escape_xml("<a href=\"x\">Tom & 'Jerry'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;"
+        );
+        assert_eq!(
+            escape_xml("bell\u{7}\ttab\u{ffff}"),
+            "bell\u{fffd}\ttab\u{fffd}"
+        );
+        assert_eq!(escape_dot("say \"hi\"\\\nbye"), "say \\\"hi\\\"\\\\\\nbye");
+    }
+
+    #[test]
+    fn exports_fragment_links_to_resource() {
+        let store = DataStore::in_memory().unwrap();
+        store
+            .ingest(&resource("file:///a.md", &["file:///b.md#intro"], &[]))
+            .unwrap();
+        store.ingest(&resource("file:///b.md", &[], &[])).unwrap();
+
+        let mut output = vec![];
+        export(&store, Format::Dot, &mut output).unwrap();
+        let dot = String::from_utf8(output).unwrap();
+        assert!(dot.contains("\"file:///a.md\" -> \"file:///b.md\""));
+        assert_eq!(dot.matches("[label=").count(), 2);
+
+        let mut output = vec![];
+        export(&store, Format::GraphML, &mut output).unwrap();
+        let graphml = String::from_utf8(output).unwrap();
+        assert_eq!(graphml.matches("<node ").count(), 2);
+    }
+}
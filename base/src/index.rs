@@ -1,26 +1,43 @@
-use crate::data::SimilarResource;
-use dirs;
+use crate::data::{
+    FacetCount, Facets, InputFilter, SearchResult, SimilarResource, SimilarityWeights,
+};
+use crate::language::Language;
 use log;
+use serde_json;
 use std::convert::From;
+use std::ffi::OsStr;
 use std::fmt;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::string::FromUtf8Error;
 use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
-use stopwords::{Stopwords, NLTK};
-use tantivy::collector::TopDocs;
+use tantivy::collector::{Count, FacetCollector, FacetCounts, TopDocs};
 use tantivy::directory;
-use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, Occur, Query, QueryParser, QueryParserError, TermQuery,
+};
 use tantivy::schema;
-use tantivy::schema::{IndexRecordOption, TextFieldIndexing, TextOptions};
+use tantivy::schema::{Facet, IndexRecordOption, TextFieldIndexing, TextOptions};
 use tantivy::tokenizer;
-use tantivy::{Index, IndexReader, IndexWriter, Opstamp, Term};
+use tantivy::tokenizer::TokenizerManager;
+use tantivy::{Directory, Index, IndexReader, IndexWriter, Opstamp, SnippetGenerator, Term};
 use tique::topterms::{Keywords, TopTerms};
 
+/// Name of the analyzer used for fields shared by all languages. It does not
+/// stem terms so that extracted keywords are actual words.
+const MULTILINGUAL: &str = "multilingual";
+
 #[derive(Clone)]
 struct Schema {
     url: schema::Field,
     title: schema::Field,
     body: schema::Field,
+    language: schema::Field,
+    tags: schema::Field,
+    urls: schema::Field,
+    /// Language specific fields, with stemmed title & body of the documents
+    /// in that language.
+    stemmed: Vec<(Language, schema::Field)>,
     schema: schema::Schema,
 }
 impl fmt::Debug for Schema {
@@ -30,28 +47,40 @@ impl fmt::Debug for Schema {
 }
 
 impl Schema {
-    pub fn tokenizer() -> tokenizer::TextAnalyzer {
-        let words = NLTK::stopwords(stopwords::Language::English)
-            .unwrap()
-            .iter()
-            .map(|word| word.to_string())
-            .collect();
-
+    /// Analyzer for the fields shared by all languages, removing stopwords of
+    /// the given language. If language is not known no stopwords are removed,
+    /// as words that are stopwords in one language can be significant in the
+    /// other.
+    pub fn tokenizer(language: Option<Language>) -> tokenizer::TextAnalyzer {
+        let analyzer = tokenizer::TextAnalyzer::from(tokenizer::SimpleTokenizer)
+            .filter(tokenizer::RemoveLongFilter::limit(40))
+            .filter(tokenizer::LowerCaser);
+        match language {
+            Some(language) => {
+                analyzer.filter(tokenizer::StopWordFilter::remove(language.stopwords()))
+            }
+            None => analyzer,
+        }
+    }
+    /// Analyzer for the language specific fields, which unlike the shared
+    /// one stems the terms.
+    pub fn stemming_tokenizer(language: Language) -> tokenizer::TextAnalyzer {
         tokenizer::TextAnalyzer::from(tokenizer::SimpleTokenizer)
             .filter(tokenizer::RemoveLongFilter::limit(40))
             .filter(tokenizer::LowerCaser)
-            .filter(tokenizer::StopWordFilter::remove(words))
-        // Disable stemmer as keywords appear to be stems instead of
-        // actual terms.
-        // .filter(tokenizer::Stemmer::new(tokenizer::Language::English))
+            .filter(tokenizer::StopWordFilter::remove(language.stopwords()))
+            .filter(language.stemmer())
+    }
+    fn stemming_tokenizer_name(language: Language) -> String {
+        format!("{:}_stemmed", language.code())
     }
-    pub fn indexer() -> TextFieldIndexing {
+    pub fn indexer(tokenizer: &str) -> TextFieldIndexing {
         TextFieldIndexing::default()
-            .set_tokenizer("en_with_stopwords")
+            .set_tokenizer(tokenizer)
             .set_index_option(IndexRecordOption::WithFreqsAndPositions)
     }
-    pub fn text_options() -> TextOptions {
-        schema::TextOptions::default().set_indexing_options(Schema::indexer())
+    pub fn text_options(tokenizer: &str) -> TextOptions {
+        schema::TextOptions::default().set_indexing_options(Schema::indexer(tokenizer))
     }
     pub fn new() -> Self {
         let mut schema = schema::SchemaBuilder::default();
@@ -64,36 +93,120 @@ impl Schema {
         // frequency and term positions.
         // Field also has `STORED` flag which means that the field will also be
         // saved in a compressed row-oriented key-value store.
-        let title = schema.add_text_field("title", Schema::text_options());
+        let title = schema.add_text_field("title", Schema::text_options(MULTILINGUAL));
 
         // Field `body` corresponds to content of the resource. It will have
         // full-text search, but not an ability to reconstruct it.
-        let body = schema.add_text_field("body", Schema::text_options().set_stored());
+        let body = schema.add_text_field("body", Schema::text_options(MULTILINGUAL).set_stored());
+
+        // ISO 639-1 code of the detected document language.
+        let language = schema.add_text_field("language", schema::STRING | schema::STORED);
+
+        // Tags of the resource as facets, nested tags like `project/ksp`
+        // map to the facet path `/project/ksp`.
+        let tags = schema.add_facet_field("tags");
+
+        // URL path hierarchy as a facet, so that resources can be filtered by
+        // the URL prefix e.g. `file:///Users/me/notes/a.md` maps to the facet
+        // path `/file:\/\//Users/me/notes/a.md`.
+        let urls = schema.add_facet_field("urls");
+
+        // Stemmed title & body, only set for documents in the field language.
+        let stemmed = Language::ALL
+            .iter()
+            .map(|language| {
+                let name = format!("text_{:}", language.code());
+                let tokenizer = Schema::stemming_tokenizer_name(*language);
+                let field = schema.add_text_field(&name, Schema::text_options(&tokenizer));
+                (*language, field)
+            })
+            .collect();
 
         Schema {
             url,
             title,
             body,
+            language,
+            tags,
+            urls,
+            stemmed,
             schema: schema.build(),
         }
     }
 
+    /// Opens index in the given directory. Index created with a different
+    /// schema (by an older version) can not be read, in which case it needs
+    /// to be rebuilt with `reindex`.
     fn index(&self, path: &Path) -> Result<Index, Error> {
         let directory = tantivy::directory::MmapDirectory::open(path)?;
-        let index = tantivy::Index::open_or_create(directory, self.schema.clone())?;
+        let index = match tantivy::Index::open_or_create(directory, self.schema.clone()) {
+            Err(tantivy::TantivyError::SchemaError(message)) => {
+                return Err(Error::OutdatedSchema(message))
+            }
+            result => result?,
+        };
+        Ok(self.register_tokenizers(index))
+    }
 
+    fn ram_index(&self) -> Index {
+        let index = tantivy::Index::create_in_ram(self.schema.clone());
+        self.register_tokenizers(index)
+    }
+
+    fn register_tokenizers(&self, index: Index) -> Index {
+        self.register(index.tokenizers(), MultilingualTokenizer::new().into());
         index
-            .tokenizers()
-            .register("en_with_stopwords", Schema::tokenizer());
+    }
+
+    /// Tokenizers used to parse the query, which remove stopwords of the
+    /// query language instead of the language of each indexed document.
+    fn query_tokenizers(&self, query: &str) -> TokenizerManager {
+        let tokenizers = TokenizerManager::default();
+        self.register(&tokenizers, Schema::tokenizer(Language::detect(query)));
+        tokenizers
+    }
 
-        Ok(index)
+    fn register(&self, tokenizers: &TokenizerManager, multilingual: tokenizer::TextAnalyzer) {
+        tokenizers.register(MULTILINGUAL, multilingual);
+        for (language, _) in self.stemmed.iter() {
+            tokenizers.register(
+                &Schema::stemming_tokenizer_name(*language),
+                Schema::stemming_tokenizer(*language),
+            );
+        }
+    }
+
+    /// Fields searched by the full-text search queries.
+    fn search_fields(&self) -> Vec<schema::Field> {
+        let mut fields = vec![self.title, self.body];
+        fields.extend(self.stemmed.iter().map(|(_, field)| *field));
+        fields
     }
 
-    fn document(&self, url: &str, title: &str, body: &str) -> Result<schema::Document, Error> {
+    fn document(
+        &self,
+        url: &str,
+        title: &str,
+        body: &str,
+        language: Option<&str>,
+        tags: &[String],
+    ) -> Result<schema::Document, Error> {
         let mut document = schema::Document::new();
         document.add_text(self.url, url);
+        document.add_facet(self.urls, url_facet(url));
         document.add_text(self.title, title);
         document.add_text(self.body, body);
+        for tag in tags {
+            document.add_facet(self.tags, tag_facet(tag));
+        }
+        if let Some(code) = language {
+            document.add_text(self.language, code);
+            let language = Language::from_code(code);
+            if let Some((_, field)) = self.stemmed.iter().find(|(l, _)| Some(*l) == language) {
+                document.add_text(*field, title);
+                document.add_text(*field, body);
+            }
+        }
         Ok(document)
     }
 
@@ -101,67 +214,361 @@ impl Schema {
         let value = doc.get_first(self.url).ok_or(Error::URLReadError)?;
         value.text().map(String::from).ok_or(Error::URLReadError)
     }
+
+    fn document_tags(&self, doc: &schema::Document) -> Vec<String> {
+        doc.get_all(self.tags)
+            .into_iter()
+            .filter_map(|value| match value {
+                schema::Value::Facet(facet) => Some(facet_tag(facet)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Tokenizer for the fields shared by all languages. Documents of different
+/// languages share those fields, so stopwords of the language detected for
+/// the tokenized text are removed.
+#[derive(Clone)]
+struct MultilingualTokenizer {
+    analyzers: Vec<(Language, tokenizer::TextAnalyzer)>,
+    fallback: tokenizer::TextAnalyzer,
+}
+
+impl MultilingualTokenizer {
+    fn new() -> Self {
+        MultilingualTokenizer {
+            analyzers: Language::ALL
+                .iter()
+                .map(|language| (*language, Schema::tokenizer(Some(*language))))
+                .collect(),
+            fallback: Schema::tokenizer(None),
+        }
+    }
+}
+
+impl tokenizer::Tokenizer for MultilingualTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> tokenizer::BoxTokenStream<'a> {
+        let language = Language::detect(text);
+        self.analyzers
+            .iter()
+            .find(|(known, _)| Some(*known) == language)
+            .map(|(_, analyzer)| analyzer)
+            .unwrap_or(&self.fallback)
+            .token_stream(text)
+    }
+}
+
+/// Maps tag to a facet, so that `project/ksp` becomes `/project/ksp`.
+fn tag_facet(tag: &str) -> Facet {
+    Facet::from_path(tag.split('/').filter(|segment| !segment.is_empty()))
+}
+
+fn facet_tag(facet: &Facet) -> String {
+    facet.to_path().join("/")
+}
+
+/// Maps URL to a facet with the scheme & authority as the first segment
+/// followed by the path segments, ignoring query and fragment.
+fn url_facet(url: &str) -> Facet {
+    let end = url.find(|c| c == '?' || c == '#').unwrap_or(url.len());
+    let url = &url[..end];
+    let (origin, path) = match url.find("://") {
+        Some(index) => {
+            let start = index + 3;
+            let path_start = url[start..].find('/').map_or(url.len(), |i| start + i);
+            (&url[..path_start], &url[path_start..])
+        }
+        None => ("", url),
+    };
+    let segments = std::iter::once(origin)
+        .chain(path.split('/'))
+        .filter(|segment| !segment.is_empty());
+    Facet::from_path(segments)
+}
+
+fn facet_url(facet: &Facet) -> String {
+    let path = facet.to_path();
+    match path.split_first() {
+        Some((origin, segments)) if origin.contains("://") => {
+            format!("{:}/{:}", origin, segments.join("/"))
+        }
+        _ => path.join("/"),
+    }
+}
+
+/// Resource as it was indexed.
+#[derive(Clone, Debug)]
+pub struct IndexedDocument {
+    pub body: String,
+    pub tags: Vec<String>,
 }
 
 pub struct IndexService {
     schema: Schema,
+    index: Index,
     reader: IndexReader,
     writer: Arc<RwLock<IndexWriter>>,
     topterms: TopTerms,
 }
 
 impl IndexService {
-    pub fn open() -> Result<Self, Error> {
-        let mut path = dirs::home_dir().expect("Unable to locate user home directory");
-        path.push(".knowledge-service");
-        path.push("tantivy");
-        std::fs::create_dir_all(&path)?;
-        IndexService::activate(&path)
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        std::fs::create_dir_all(path)?;
+        IndexService::activate(path)
+    }
+    /// Creates an empty index in the given directory, discarding whatever
+    /// was in it. Used to rebuild index that can not be opened.
+    pub fn create(path: &Path) -> Result<Self, Error> {
+        if path.exists() {
+            log::warn!("Discarding index at {:?}", path);
+            std::fs::remove_dir_all(path)?;
+        }
+        IndexService::open(path)
     }
     pub fn activate(path: &Path) -> Result<Self, Error> {
         let schema = Schema::new();
         let index = schema.index(path)?;
-
+        IndexService::from_index(schema, index)
+    }
+    /// Creates an index that is kept in memory and is gone once service is
+    /// dropped.
+    pub fn in_memory() -> Result<Self, Error> {
+        let schema = Schema::new();
+        let index = schema.ram_index();
+        IndexService::from_index(schema, index)
+    }
+    fn from_index(schema: Schema, index: Index) -> Result<Self, Error> {
         let topterms = TopTerms::new(&index, vec![schema.body])?;
         let reader = index.reader()?;
         let writer = Arc::new(RwLock::new(index.writer(50_000_000)?));
         Ok(IndexService {
             schema,
+            index,
             reader,
             writer,
             topterms,
         })
     }
-    pub async fn ingest(&self, url: &str, title: &str, body: &str) -> Result<Opstamp, Error> {
+    pub async fn ingest(
+        &self,
+        url: &str,
+        title: &str,
+        body: &str,
+        language: Option<&str>,
+        tags: &[String],
+    ) -> Result<Opstamp, Error> {
+        // Document is created before anything is staged, so that we never
+        // stage deletion without a replacement.
+        let doc = self.schema.document(url, title, body, language, tags)?;
         let writer = self.writer.read()?;
         // Delete all documents with the matching url before adding a new document.
         writer.delete_term(Term::from_field_text(self.schema.url, &url));
-        let doc = self.schema.document(url, title, body)?;
         Ok(writer.add_document(doc))
     }
+    pub async fn forget(&self, url: &str) -> Result<Opstamp, Error> {
+        let writer = self.writer.read()?;
+        Ok(writer.delete_term(Term::from_field_text(self.schema.url, &url)))
+    }
+    /// Returns URLs of all the indexed documents.
+    pub fn indexed_urls(&self) -> Result<Vec<String>, Error> {
+        let searcher = self.reader.searcher();
+        let limit = searcher.num_docs() as usize;
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+        let top_docs = searcher.search(&AllQuery, &TopDocs::with_limit(limit))?;
+        let mut urls = Vec::with_capacity(top_docs.len());
+        for (_, address) in top_docs {
+            let doc = searcher.doc(address)?;
+            urls.push(self.schema.document_url(doc)?);
+        }
+        Ok(urls)
+    }
     pub async fn commit(&self) -> Result<Opstamp, Error> {
         let mut writer = self.writer.write()?;
-        Ok(writer.commit()?)
+        let opstamp = writer.commit()?;
+        // Reader is otherwise reloaded in the background, which would leave
+        // `check` right after `check --repair` reporting what was repaired.
+        self.reader.reload()?;
+        Ok(opstamp)
+    }
+    /// Commits pending changes and copies the committed index into the `dest`
+    /// directory, calling `copy` before it does. Writer is held until both
+    /// copies are complete so that nothing is staged or committed in the
+    /// meantime, which lets `copy` take a copy of the data index is built
+    /// from that is consistent with it.
+    pub async fn backup<F, E>(&self, dest: &Path, copy: F) -> Result<Opstamp, E>
+    where
+        F: FnOnce() -> Result<(), E>,
+        E: From<Error>,
+    {
+        let mut writer = self.writer.write().map_err(Error::from)?;
+        writer.commit().map_err(Error::from)?;
+        copy()?;
+        Ok(copy_committed(&self.index, dest)?)
+    }
+    /// Searches title and body of the indexed resources using the query
+    /// syntax of the Tantivy `QueryParser`. Returns matches ranked by score,
+    /// skipping first `offset` ones, along with the total number of matches.
+    pub fn search(
+        &self,
+        query: &str,
+        filter: &InputFilter,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<SearchResult>, usize, Facets), Error> {
+        let searcher = self.reader.searcher();
+        let parser = QueryParser::new(
+            self.schema.schema.clone(),
+            self.schema.search_fields(),
+            self.schema.query_tokenizers(query),
+        );
+        let query = self.filter_query(parser.parse_query(query)?, filter);
+
+        // Offset and limit come from the client, and collector allocates room
+        // for all the matches up front, so they are capped by the number of
+        // documents there are.
+        let num_docs = searcher.num_docs() as usize;
+        let offset = offset.min(num_docs);
+        let limit = limit.min(num_docs - offset);
+        // Collector panics if limit is zero, so at least one match is always
+        // collected even though none may be returned. That way empty page
+        // still reports total count and facets.
+        let (top_docs, total_count, tag_counts, url_counts) = searcher.search(
+            &query,
+            &(
+                TopDocs::with_limit((offset + limit).max(1)),
+                Count,
+                self.tag_collector(filter),
+                self.url_collector(filter),
+            ),
+        )?;
+        let generator = SnippetGenerator::create(&searcher, &*query, self.schema.body)?;
+        let mut results = Vec::new();
+        for (score, address) in top_docs.into_iter().skip(offset).take(limit) {
+            let doc = searcher.doc(address)?;
+            let snippet = generator.snippet_from_doc(&doc);
+            let url = self.schema.document_url(doc)?;
+            results.push(SearchResult {
+                score,
+                url,
+                snippet: if snippet.fragments().is_empty() {
+                    None
+                } else {
+                    Some(snippet.to_html())
+                },
+            });
+        }
+        let facets = self.facets(filter, &tag_counts, &url_counts);
+        Ok((results, total_count, facets))
+    }
+    /// Restricts results of the query to the ones passing the filter.
+    fn filter_query(&self, query: Box<dyn Query>, filter: &InputFilter) -> Box<dyn Query> {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, query)];
+        for tag in filter.tags.iter().flatten() {
+            let term = Term::from_facet(self.schema.tags, &tag_facet(tag));
+            clauses.push((Occur::Must, term_query(term)));
+        }
+        for tag in filter.exclude_tags.iter().flatten() {
+            let term = Term::from_facet(self.schema.tags, &tag_facet(tag));
+            clauses.push((Occur::MustNot, term_query(term)));
+        }
+        if let Some(prefix) = filter.url_prefix.as_ref() {
+            let term = Term::from_facet(self.schema.urls, &url_facet(prefix));
+            clauses.push((Occur::Must, term_query(term)));
+        }
+        Box::new(BooleanQuery::from(clauses))
+    }
+    fn tag_collector(&self, filter: &InputFilter) -> FacetCollector {
+        let mut collector = FacetCollector::for_field(self.schema.tags);
+        collector.add_facet(Facet::root());
+        for tag in filter.tags.iter().flatten() {
+            collector.add_facet(tag_facet(tag));
+        }
+        collector
+    }
+    fn url_collector(&self, filter: &InputFilter) -> FacetCollector {
+        let mut collector = FacetCollector::for_field(self.schema.urls);
+        collector.add_facet(url_prefix_facet(filter));
+        collector
+    }
+    fn facets(
+        &self,
+        filter: &InputFilter,
+        tag_counts: &FacetCounts,
+        url_counts: &FacetCounts,
+    ) -> Facets {
+        let mut tag_facets = vec![Facet::root()];
+        tag_facets.extend(filter.tags.iter().flatten().map(|tag| tag_facet(tag)));
+        let mut tags: Vec<FacetCount> = Vec::new();
+        for facet in tag_facets {
+            for (facet, count) in tag_counts.get(facet) {
+                let name = facet_tag(facet);
+                if !tags.iter().any(|tag| tag.name == name) {
+                    tags.push(FacetCount {
+                        name,
+                        count: count as i32,
+                    });
+                }
+            }
+        }
+        let urls = url_counts
+            .get(url_prefix_facet(filter))
+            .map(|(facet, count)| FacetCount {
+                name: facet_url(facet),
+                count: count as i32,
+            })
+            .collect();
+        Facets { tags, urls }
+    }
+    /// Returns stored body and tags of the document indexed for the given url.
+    pub fn find_document(&self, url: &str) -> Result<Option<IndexedDocument>, Error> {
+        let searcher = self.reader.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(self.schema.url, url),
+            IndexRecordOption::Basic,
+        );
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(1))?;
+        match top_docs.first() {
+            Some((_, address)) => {
+                let doc = searcher.doc(*address)?;
+                let body = doc
+                    .get_first(self.schema.body)
+                    .and_then(|value| value.text())
+                    .ok_or_else(|| Error::MissingField(String::from("body")))?
+                    .to_string();
+                let tags = self.schema.document_tags(&doc);
+                Ok(Some(IndexedDocument { body, tags }))
+            }
+            None => Ok(None),
+        }
     }
     pub fn extract_keywords(&self, content: &str, limit: usize) -> Keywords {
         self.topterms.extract(limit, content)
     }
+    /// Finds resources matching keywords in their content and title or
+    /// sharing tags with the source, each kind of match scored according to
+    /// the given weights. Matches with zero weight are not considered.
     pub fn search_with_keywords(
         &self,
         source_url: &str,
         keywords: &Keywords,
+        tags: &[String],
+        weights: &SimilarityWeights,
+        filter: &InputFilter,
         limit: usize,
     ) -> Result<Vec<SimilarResource>, Error> {
         let searcher = self.reader.searcher();
-        let keyword_query: Box<dyn Query> = Box::new(keywords.clone().into_query());
-        let source_query = Box::new(TermQuery::new(
-            Term::from_field_text(self.schema.url, source_url),
-            IndexRecordOption::Basic,
-        ));
-        let query = BooleanQuery::from(vec![
-            (Occur::Must, keyword_query),
-            (Occur::MustNot, source_query),
-        ]);
+        // Collector allocates room for all the matches up front, so limit is
+        // capped by the number of documents there are. It also panics if
+        // limit is zero.
+        let limit = limit.min(searcher.num_docs() as usize);
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+        let query = self.similarity_query(source_url, keywords, tags, weights);
+        let query = self.filter_query(query, filter);
 
         let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
         let mut similar = Vec::new();
@@ -177,6 +584,92 @@ impl IndexService {
         }
         Ok(similar)
     }
+    /// Returns facet counts of the resources similar to the source.
+    pub fn similar_facets(
+        &self,
+        source_url: &str,
+        keywords: &Keywords,
+        tags: &[String],
+        weights: &SimilarityWeights,
+        filter: &InputFilter,
+    ) -> Result<Facets, Error> {
+        let searcher = self.reader.searcher();
+        let query = self.similarity_query(source_url, keywords, tags, weights);
+        let query = self.filter_query(query, filter);
+        let (tag_counts, url_counts) = searcher.search(
+            &query,
+            &(self.tag_collector(filter), self.url_collector(filter)),
+        )?;
+        Ok(self.facets(filter, &tag_counts, &url_counts))
+    }
+    fn similarity_query(
+        &self,
+        source_url: &str,
+        keywords: &Keywords,
+        tags: &[String],
+        weights: &SimilarityWeights,
+    ) -> Box<dyn Query> {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        if weights.body > 0.0 {
+            let keyword_query = Box::new(keywords.clone().into_query());
+            clauses.push((Occur::Should, boost(keyword_query, weights.body)));
+        }
+        if weights.title > 0.0 {
+            let title_terms = keywords
+                .terms()
+                .map(|term| Term::from_field_text(self.schema.title, term.text()));
+            if let Some(query) = any_term(title_terms) {
+                clauses.push((Occur::Should, boost(query, weights.title)));
+            }
+        }
+        if weights.tags > 0.0 {
+            let tag_terms = tags
+                .iter()
+                .map(|tag| Term::from_facet(self.schema.tags, &tag_facet(tag)));
+            if let Some(query) = any_term(tag_terms) {
+                clauses.push((Occur::Should, boost(query, weights.tags)));
+            }
+        }
+        let source_query = Box::new(TermQuery::new(
+            Term::from_field_text(self.schema.url, source_url),
+            IndexRecordOption::Basic,
+        ));
+        clauses.push((Occur::MustNot, source_query));
+        Box::new(BooleanQuery::from(clauses))
+    }
+}
+
+fn url_prefix_facet(filter: &InputFilter) -> Facet {
+    filter
+        .url_prefix
+        .as_ref()
+        .map(|prefix| url_facet(prefix))
+        .unwrap_or_else(Facet::root)
+}
+
+fn term_query(term: Term) -> Box<dyn Query> {
+    Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+}
+
+fn boost(query: Box<dyn Query>, weight: f32) -> Box<dyn Query> {
+    Box::new(BoostQuery::new(query, weight))
+}
+
+/// Creates query matching documents with any of the given terms, or `None`
+/// if there are no terms.
+fn any_term(terms: impl Iterator<Item = Term>) -> Option<Box<dyn Query>> {
+    let clauses: Vec<(Occur, Box<dyn Query>)> = terms
+        .map(|term| {
+            let query: Box<dyn Query> =
+                Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs));
+            (Occur::Should, query)
+        })
+        .collect();
+    if clauses.is_empty() {
+        None
+    } else {
+        Some(Box::new(BooleanQuery::from(clauses)))
+    }
 }
 
 impl fmt::Debug for IndexService {
@@ -187,6 +680,130 @@ impl fmt::Debug for IndexService {
     }
 }
 
+/// Copies last commit of the index at `path` into the `dest` directory. Unlike
+/// `IndexService::backup` it does not need the index writer, so it can be used
+/// while index is open by a running server.
+pub fn backup(path: &Path, dest: &Path) -> Result<Opstamp, Error> {
+    let index = Index::open_in_dir(path)?;
+    copy_committed(&index, dest)
+}
+
+/// Copy of the index made by `backup`, staged next to the index at `path`
+/// until it is swapped in by `commit`. Writer lock of the index at `path` is
+/// held all along, so that it can not be used in the meantime. Staged copy is
+/// removed if restore is dropped without being committed.
+pub struct Restore {
+    path: PathBuf,
+    staging: PathBuf,
+    _lock: directory::DirectoryLock,
+}
+
+/// Stages restore of the index at `path` from the copy made by `backup`. Fails
+/// if index is open by a running server, because it can not be replaced under
+/// its writer.
+pub fn restore(path: &Path, source: &Path) -> Result<Restore, Error> {
+    // Make sure source is an index before anything is copied.
+    Index::open_in_dir(source)?;
+
+    std::fs::create_dir_all(path)?;
+    let directory = directory::MmapDirectory::open(path)?;
+    let lock = directory
+        .acquire_lock(&directory::INDEX_WRITER_LOCK)
+        .map_err(|error| tantivy::TantivyError::LockFailure(error, None))?;
+
+    let staging = path.with_extension("restore");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+    let restore = Restore {
+        path: path.to_path_buf(),
+        staging,
+        _lock: lock,
+    };
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && !is_lock_file(&entry.file_name()) {
+            std::fs::copy(entry.path(), restore.staging.join(entry.file_name()))?;
+        }
+    }
+    Ok(restore)
+}
+
+impl Restore {
+    /// Moves the staged copy into the index. Its `meta.json` is moved last,
+    /// which switches index over to the copy at once, so if anything fails
+    /// before that index is left as it was. Files of the replaced index are
+    /// removed afterwards. Lock files are left in place, as the writer lock is
+    /// still held.
+    pub fn commit(self) -> Result<(), Error> {
+        let meta = OsStr::new("meta.json");
+        let mut staged = Vec::new();
+        for entry in std::fs::read_dir(&self.staging)? {
+            staged.push(entry?.file_name());
+        }
+        let mut replaced = Vec::new();
+        for entry in std::fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if entry.file_type()?.is_file() && !is_lock_file(&name) && !staged.contains(&name) {
+                replaced.push(entry.path());
+            }
+        }
+
+        for name in staged.iter().filter(|name| name.as_os_str() != meta) {
+            std::fs::rename(self.staging.join(name), self.path.join(name))?;
+        }
+        std::fs::rename(self.staging.join(meta), self.path.join(meta))?;
+
+        for path in replaced {
+            if let Err(error) = std::fs::remove_file(&path) {
+                log::warn!("Failed to remove {:?}: {:}", path, error);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_dir_all(&self.staging) {
+            log::warn!("Failed to remove {:?}: {:}", self.staging, error);
+        }
+    }
+}
+
+fn is_lock_file(name: &OsStr) -> bool {
+    let name = Path::new(name);
+    name == directory::INDEX_WRITER_LOCK.filepath || name == directory::META_LOCK.filepath
+}
+
+/// Copies files of the last commit and its meta into the `dest` directory.
+/// Meta lock is held during the copy, which prevents garbage collection from
+/// removing files of the commit, e.g. when segments get merged.
+fn copy_committed(index: &Index, dest: &Path) -> Result<Opstamp, Error> {
+    let directory = index.directory();
+    let _lock = directory
+        .acquire_lock(&directory::META_LOCK)
+        .map_err(|error| tantivy::TantivyError::LockFailure(error, None))?;
+    let metas = index.load_metas()?;
+
+    std::fs::create_dir_all(dest)?;
+    for segment in metas.segments.iter() {
+        for path in segment.list_files() {
+            let source = directory
+                .open_read(&path)
+                .map_err(tantivy::TantivyError::from)?;
+            std::fs::write(dest.join(&path), source.as_slice())?;
+        }
+    }
+    let meta = serde_json::to_vec_pretty(&metas)
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+    std::fs::write(dest.join("meta.json"), meta)?;
+
+    Ok(metas.opstamp)
+}
+
 #[derive(Debug)]
 pub enum Error {
     IndexError(tantivy::TantivyError),
@@ -197,6 +814,8 @@ pub enum Error {
     URLReadError,
     MissingField(String),
     IOError(std::io::Error),
+    QueryError(QueryParserError),
+    OutdatedSchema(String),
 }
 
 impl std::error::Error for Error {}
@@ -216,6 +835,12 @@ impl fmt::Display for Error {
                 name
             ),
             Error::IOError(error) => error.fmt(f),
+            Error::QueryError(error) => write!(f, "Invalid search query: {:?}", error),
+            Error::OutdatedSchema(message) => write!(
+                f,
+                "Index has an outdated schema ({:}), run `reindex --recreate` to rebuild it",
+                message
+            ),
         }
     }
 }
@@ -250,6 +875,12 @@ impl From<FromUtf8Error> for Error {
     }
 }
 
+impl From<QueryParserError> for Error {
+    fn from(error: QueryParserError) -> Self {
+        Error::QueryError(error)
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
         Error::IOError(error)
@@ -0,0 +1,66 @@
+use stopwords::{Stopwords, NLTK};
+use tantivy::tokenizer;
+use whatlang;
+
+/// Languages that have dedicated analyzers in the index. Resources in other
+/// languages are still indexed, just without language specific stemming.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Language {
+    English,
+    German,
+    Spanish,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::English, Language::German, Language::Spanish];
+
+    /// ISO 639-1 code of the language, which is how language is stored and
+    /// exposed through GraphQL.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::Spanish => "es",
+        }
+    }
+    pub fn from_code(code: &str) -> Option<Self> {
+        Language::ALL
+            .iter()
+            .find(|language| language.code() == code)
+            .copied()
+    }
+    /// Detects language of the given text. Returns `None` if text is in the
+    /// language without a dedicated analyzer or if it could not be reliably
+    /// detected.
+    pub fn detect(text: &str) -> Option<Self> {
+        let info = whatlang::detect(text)?;
+        if !info.is_reliable() {
+            return None;
+        }
+        match info.lang() {
+            whatlang::Lang::Eng => Some(Language::English),
+            whatlang::Lang::Deu => Some(Language::German),
+            whatlang::Lang::Spa => Some(Language::Spanish),
+            _ => None,
+        }
+    }
+    pub fn stopwords(&self) -> Vec<String> {
+        let language = match self {
+            Language::English => stopwords::Language::English,
+            Language::German => stopwords::Language::German,
+            Language::Spanish => stopwords::Language::Spanish,
+        };
+        NLTK::stopwords(language)
+            .unwrap_or(&[])
+            .iter()
+            .map(|word| word.to_string())
+            .collect()
+    }
+    pub fn stemmer(&self) -> tokenizer::Stemmer {
+        tokenizer::Stemmer::new(match self {
+            Language::English => tokenizer::Language::English,
+            Language::German => tokenizer::Language::German,
+            Language::Spanish => tokenizer::Language::Spanish,
+        })
+    }
+}
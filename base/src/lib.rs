@@ -1,6 +1,16 @@
+pub mod backup;
+pub mod check;
+pub mod config;
+pub mod content;
 pub mod data;
+pub mod graph;
 mod index;
+pub mod language;
+mod migration;
+mod pagination;
 pub mod schema;
 pub mod server;
 pub mod service;
 mod store;
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,359 @@
+use log;
+use rusqlite::{Connection, Transaction, TransactionBehavior, NO_PARAMS};
+use std::fmt;
+use std::io;
+
+/// Schema change of the knowledge base. Migrations are applied in the order of
+/// their versions and each one is applied exactly once, after which
+/// `user_version` of the database is set to the version of the migration.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// All the known migrations, ordered by version. New schema changes must be
+/// appended here with a next version number and never edited once released.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create tables",
+        sql: include_str!("../sql/migrations/0001_create_tables.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "resource ingest time",
+        sql: include_str!("../sql/migrations/0002_resource_ingest_time.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "resource timestamps",
+        sql: include_str!("../sql/migrations/0003_resource_timestamps.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "revisions",
+        sql: include_str!("../sql/migrations/0004_revisions.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "resource cid index",
+        sql: include_str!("../sql/migrations/0005_resource_cid_index.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "resource language",
+        sql: include_str!("../sql/migrations/0006_resource_language.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "contents",
+        sql: include_str!("../sql/migrations/0007_contents.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "resource content flag",
+        sql: include_str!("../sql/migrations/0008_resource_content_flag.sql"),
+    },
+];
+
+/// Databases created before `user_version` was tracked had `resources` table
+/// without `icon` and `image` columns. They report version `0` even though they
+/// do have tables, so they need to be brought up to date before the first
+/// migration is applied.
+const LEGACY_MIGRATION: &str = include_str!("../sql/migrations/legacy_resource_media.sql");
+
+/// Version of the schema this build of the knowledge base expects.
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn user_version(connection: &Connection) -> Result<i32, Error> {
+    Ok(connection.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+/// Brings database schema up to date by applying all the pending migrations.
+/// Each migration is applied in its own transaction, so if one fails database
+/// is left at the last successfully migrated version. Returns the version
+/// database ended up at.
+pub fn migrate(connection: &mut Connection) -> Result<i32, Error> {
+    let supported = latest_version();
+    let found = user_version(connection)?;
+    if found > supported {
+        return Err(Error::UnsupportedVersion { found, supported });
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > found) {
+        // Immediate transaction acquires write lock up front so that concurrent
+        // processes opening the same database do not apply migration twice.
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let current = user_version(&transaction)?;
+        if current >= migration.version {
+            continue;
+        }
+
+        log::info!(
+            "Migrating database from version {:} to {:} ({:})",
+            current,
+            migration.version,
+            migration.name
+        );
+        apply(&transaction, current, migration).map_err(|error| Error::MigrationFailed {
+            version: migration.version,
+            name: migration.name,
+            error,
+        })?;
+        transaction.commit()?;
+    }
+
+    user_version(connection)
+}
+
+fn apply(
+    transaction: &Transaction<'_>,
+    current: i32,
+    migration: &Migration,
+) -> Result<(), rusqlite::Error> {
+    if current == 0 && is_legacy(transaction)? {
+        transaction.execute_batch(LEGACY_MIGRATION)?;
+    }
+    transaction.execute_batch(migration.sql)?;
+    transaction.pragma_update(None, "user_version", &migration.version)
+}
+
+fn is_legacy(connection: &Connection) -> Result<bool, rusqlite::Error> {
+    let mut select = connection.prepare("PRAGMA table_info(resources)")?;
+    let mut rows = select.query(NO_PARAMS)?;
+    let mut has_table = false;
+    while let Some(row) = rows.next()? {
+        has_table = true;
+        let column: String = row.get(1)?;
+        if column == "icon" {
+            return Ok(false);
+        }
+    }
+    Ok(has_table)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    SQLError(rusqlite::Error),
+    UnsupportedVersion {
+        found: i32,
+        supported: i32,
+    },
+    MigrationFailed {
+        version: i32,
+        name: &'static str,
+        error: rusqlite::Error,
+    },
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::SQLError(error) => error.fmt(f),
+            Error::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Database is at version {} which is newer than supported version {}, please upgrade knowledge-server",
+                found, supported
+            ),
+            Error::MigrationFailed {
+                version,
+                name,
+                error,
+            } => write!(
+                f,
+                "Failed to migrate database to version {} ({}): {}",
+                version, name, error
+            ),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(error: rusqlite::Error) -> Self {
+        Error::SQLError(error)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        io::Error::new(io::ErrorKind::Other, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Schema of the databases created before `user_version` was tracked.
+    const LEGACY_SCHEMA: &str = "
+        CREATE TABLE resources (
+          url NOT NULL,
+          title Text,
+          description Text,
+          cid Text,
+          PRIMARY KEY (url)
+        )
+        WITHOUT ROWID;
+        CREATE TABLE inline_links (
+          referrer_url Text,
+          referrer_fragment Text,
+          referrer_location Text,
+          target_url Text NOT NULL,
+          name Text NOT NULL,
+          title Text
+        );
+        CREATE TABLE reference_links (
+          referrer_url Text,
+          referrer_fragment Text,
+          referrer_location Text,
+          target_url Text NOT NULL,
+          identifier Text NOT NULL,
+          name Text NOT NULL,
+          title Text
+        );
+        CREATE TABLE tags (
+          target_url Text NOT NULL,
+          name Text NOT NULL,
+          target_fragment Text NOT NULL,
+          target_location Text,
+          PRIMARY KEY (target_url, name, target_fragment)
+        )
+        WITHOUT ROWID;
+        CREATE VIEW view_links AS
+        SELECT referrer_url, target_url, name, 0 as kind FROM inline_links;
+    ";
+
+    /// Resource linking to the same target three times, which every version
+    /// of the schema can hold.
+    const RESOURCES: &str = "
+        INSERT INTO resources (url, title, description, cid)
+        VALUES ('file:///a.md', 'A', '', NULL);
+        INSERT INTO inline_links (referrer_url, target_url, name)
+        VALUES ('file:///a.md', 'file:///b.md', 'first');
+        INSERT INTO inline_links (referrer_url, target_url, name)
+        VALUES ('file:///a.md', 'file:///b.md', 'second');
+        INSERT INTO reference_links (referrer_url, target_url, identifier, name)
+        VALUES ('file:///a.md', 'file:///b.md', 'b', 'third');
+        INSERT INTO tags (target_url, name, target_fragment)
+        VALUES ('file:///a.md', 'project', '');
+    ";
+
+    /// Creates database at the given schema `version` as it would have been
+    /// left by an older build, with `RESOURCES` stored in it.
+    fn database_at(version: i32) -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= version) {
+            connection.execute_batch(migration.sql).unwrap();
+            connection
+                .pragma_update(None, "user_version", &migration.version)
+                .unwrap();
+        }
+        if version > 0 {
+            connection.execute_batch(RESOURCES).unwrap();
+        }
+        connection
+    }
+
+    fn count(connection: &Connection, sql: &str) -> i64 {
+        connection
+            .query_row(sql, NO_PARAMS, |row| row.get(0))
+            .unwrap()
+    }
+
+    /// Asserts that database has the latest schema and kept `RESOURCES`.
+    fn assert_migrated(connection: &Connection) {
+        assert_eq!(user_version(connection).unwrap(), latest_version());
+        assert_eq!(count(connection, "SELECT COUNT(*) FROM resources"), 1);
+        assert_eq!(count(connection, "SELECT COUNT(*) FROM view_links"), 3);
+        assert_eq!(count(connection, "SELECT COUNT(*) FROM tags"), 1);
+        // Columns added by the migrations are there.
+        count(
+            connection,
+            "SELECT COUNT(icon || image || ingested_at || created_at || modified_at || language || has_content) FROM resources",
+        );
+        count(
+            connection,
+            "SELECT COUNT(referrer_created_at || referrer_modified_at || referrer_ingested_at || referrer_language) FROM view_links",
+        );
+        count(connection, "SELECT COUNT(*) FROM contents");
+    }
+
+    #[test]
+    fn migrates_new_database() {
+        let mut connection = database_at(0);
+        assert_eq!(migrate(&mut connection).unwrap(), latest_version());
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM resources"), 0);
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM revisions"), 0);
+    }
+
+    #[test]
+    fn migrates_legacy_database() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(LEGACY_SCHEMA).unwrap();
+        connection.execute_batch(RESOURCES).unwrap();
+
+        assert_eq!(migrate(&mut connection).unwrap(), latest_version());
+        assert_migrated(&connection);
+    }
+
+    #[test]
+    fn migrates_from_every_version() {
+        for version in 1..=latest_version() {
+            let mut connection = database_at(version);
+            assert_eq!(
+                migrate(&mut connection).unwrap(),
+                latest_version(),
+                "migrating from version {:}",
+                version
+            );
+            assert_migrated(&connection);
+        }
+    }
+
+    #[test]
+    fn backfills_revisions_with_first_link_name() {
+        for version in 1..4 {
+            let mut connection = database_at(version);
+            migrate(&mut connection).unwrap();
+
+            assert_eq!(count(&connection, "SELECT COUNT(*) FROM revisions"), 1);
+            let name: String = connection
+                .query_row(
+                    "SELECT name FROM revision_links WHERE url = 'file:///a.md'",
+                    NO_PARAMS,
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(name, "first", "migrating from version {:}", version);
+        }
+    }
+
+    #[test]
+    fn migrating_twice_is_noop() {
+        let mut connection = database_at(latest_version());
+        assert_eq!(migrate(&mut connection).unwrap(), latest_version());
+        assert_eq!(migrate(&mut connection).unwrap(), latest_version());
+        assert_migrated(&connection);
+    }
+
+    #[test]
+    fn rejects_newer_database() {
+        let mut connection = database_at(latest_version());
+        connection
+            .pragma_update(None, "user_version", &(latest_version() + 1))
+            .unwrap();
+        match migrate(&mut connection) {
+            Err(Error::UnsupportedVersion { found, supported }) => {
+                assert_eq!(found, latest_version() + 1);
+                assert_eq!(supported, latest_version());
+            }
+            result => panic!("Expected unsupported version, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::data::{Page, PageInfo};
+use juniper::{FieldError, FieldResult};
+
+/// Cursor identifies position of the node in the list. Clients should treat
+/// it as an opaque string, as it's representation may change.
+pub fn encode_cursor(offset: usize) -> String {
+    format!("{:}", offset)
+}
+
+pub fn decode_cursor(cursor: &str) -> FieldResult<usize> {
+    cursor
+        .parse()
+        .map_err(|_| FieldError::from(format!("Invalid cursor {:?}", cursor)))
+}
+
+/// Returns offset of the first node following the `after` cursor.
+pub fn start_offset(after: Option<String>) -> FieldResult<usize> {
+    match after {
+        Some(cursor) => decode_cursor(&cursor)?
+            .checked_add(1)
+            .ok_or_else(|| FieldError::from(format!("Invalid cursor {:?}", cursor))),
+        None => Ok(0),
+    }
+}
+
+/// Window into an ordered list selected by the relay style `first` and
+/// `after` arguments, so that it can be pushed down into the SQL query.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Window {
+    /// Number of nodes preceding the window.
+    pub offset: usize,
+    /// Maximum number of nodes in the window, or `None` if it extends to the
+    /// end of the list.
+    pub limit: Option<usize>,
+}
+
+impl Window {
+    /// Window covering the whole list.
+    pub const ALL: Window = Window {
+        offset: 0,
+        limit: None,
+    };
+
+    pub fn new(first: Option<i32>, after: Option<String>) -> FieldResult<Self> {
+        let offset = start_offset(after)?;
+        let limit = match first {
+            Some(first) if first < 0 => {
+                return Err(FieldError::from(format!(
+                    "Argument first must be non-negative, got {:}",
+                    first
+                )))
+            }
+            Some(first) => Some(first as usize),
+            None => None,
+        };
+        Ok(Window { offset, limit })
+    }
+    /// Substitutes `:offset` and `:limit` placeholders of the query with the
+    /// bounds of the window. Negative limit means no limit in SQLite.
+    pub fn apply(&self, sql: &str) -> String {
+        let limit = self.limit.map(|limit| limit as i64).unwrap_or(-1);
+        sql.replace(":offset", &self.offset.to_string())
+            .replace(":limit", &limit.to_string())
+    }
+    /// Creates a page from the `(position, total_count, node)` records selected
+    /// by the windowed query. Positions start at `1`, and records outside of
+    /// the window (queries select the first record to report the total count
+    /// even when window is past it) are dropped.
+    pub fn page<T>(&self, records: Vec<(usize, usize, T)>) -> Page<T> {
+        let total_count = records.first().map(|(_, total, _)| *total).unwrap_or(0);
+        let offset = self.offset.min(total_count);
+        let end = match self.limit {
+            Some(limit) => offset + limit,
+            None => total_count,
+        };
+        let nodes = records
+            .into_iter()
+            .filter(|(position, _, _)| *position > offset && *position <= end)
+            .map(|(_, _, node)| node)
+            .collect();
+        Page::from_window(nodes, offset, total_count)
+    }
+}
+
+impl<T> Page<T> {
+    /// Slices the ordered list of nodes according to the relay style `first`
+    /// and `after` arguments. If `first` is omitted all the nodes following
+    /// the `after` cursor are included.
+    pub fn new(nodes: Vec<T>, first: Option<i32>, after: Option<String>) -> FieldResult<Self> {
+        let total_count = nodes.len();
+        let records = nodes
+            .into_iter()
+            .enumerate()
+            .map(|(index, node)| (index + 1, total_count, node))
+            .collect();
+        Ok(Window::new(first, after)?.page(records))
+    }
+    /// Creates a page from the nodes that were already sliced out of the list
+    /// of `total_count` nodes, starting at the given `offset`. Useful when
+    /// fetching the whole list is too costly.
+    pub fn from_window(nodes: Vec<T>, offset: usize, total_count: usize) -> Self {
+        let end = offset + nodes.len();
+        let edges: Vec<(String, T)> = nodes
+            .into_iter()
+            .enumerate()
+            .map(|(index, node)| (encode_cursor(offset + index), node))
+            .collect();
+
+        let page_info = PageInfo {
+            has_next_page: end < total_count,
+            has_previous_page: offset > 0,
+            start_cursor: edges.first().map(|(cursor, _)| cursor.clone()),
+            end_cursor: edges.last().map(|(cursor, _)| cursor.clone()),
+        };
+
+        Page {
+            edges,
+            page_info,
+            total_count,
+        }
+    }
+}
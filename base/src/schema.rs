@@ -1,10 +1,20 @@
+use crate::backup;
+use crate::config::Config;
+use crate::content;
 pub use crate::data::Mutations;
 use crate::data::{
-    InputResource, InputSimilar, Link, LinkKind, Open, Query, Resource, ResourceInfo,
-    SimilarResource, SimilarResources, Tag,
+    parent_tag_name, Facets, Graph, GraphEdge, GraphNode, IndexableResource, InputFilter,
+    InputResource, InputSimilar, Link, LinkConnection, LinkDirection, LinkEdge, LinkKind,
+    LinkOrder, Open, Page, PageInfo, Query, ReindexReport, Resource, ResourceInfo, Revision,
+    SearchConnection, SearchEdge, SearchResult, SimilarResource, SimilarResources,
+    SimilarityWeights, Snapshot, Tag, TagConnection, TagEdge, TagInfo, TagOrder,
 };
+use crate::graph;
 use crate::index::IndexService;
+use crate::language::Language;
+use crate::pagination::{start_offset, Window};
 use crate::store::DataStore;
+use chrono::{DateTime, Utc};
 pub use juniper::FieldError;
 use juniper::{FieldResult, RootNode};
 use log;
@@ -15,13 +25,22 @@ use std::sync::Arc;
 pub struct State {
     pub store: DataStore,
     pub index: Arc<IndexService>,
+    /// Configuration of the knowledge base, `None` for in-memory one.
+    pub config: Option<Config>,
 }
 impl State {
-    pub fn new() -> io::Result<Self> {
-        let store = DataStore::open()?;
-        let index = Arc::new(IndexService::open().unwrap());
+    pub fn new(config: &Config) -> io::Result<Self> {
+        let store = DataStore::open(&config.store_path())?;
+        let index = Arc::new(
+            IndexService::open(&config.index_path())
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?,
+        );
 
-        Ok(State { store, index })
+        Ok(State {
+            store,
+            index,
+            config: Some(config.clone()),
+        })
     }
 
     // pub async fn execute<'a>(&'a self, request: &'a GraphQLRequest) -> GraphQLResponse<'a> {
@@ -32,6 +51,38 @@ impl State {
 }
 impl juniper::Context for State {}
 
+/// Finds tags with a given name, along with the tags nested under it if
+/// `descendants` is set.
+async fn find_tags(
+    state: &State,
+    name: &str,
+    descendants: bool,
+    order: TagOrder,
+    window: Window,
+) -> FieldResult<Page<Tag>> {
+    if descendants {
+        state.store.select_tags_by_prefix(name, order, window)
+    } else {
+        state.store.find_tags_by_name(name, order, window).await
+    }
+}
+
+/// Finds `first` tags that are applied to the same resources as the tag with a
+/// given name, most common first.
+async fn find_related_tags(state: &State, name: &str, first: i32) -> FieldResult<Vec<TagInfo>> {
+    let mut tags = state.store.find_related_tags(name).await?;
+    tags.truncate(first.max(0) as usize);
+    Ok(tags)
+}
+
+/// Finds tag that the tag with a given name is nested under.
+async fn find_parent_tag(state: &State, name: &str) -> FieldResult<Option<TagInfo>> {
+    match parent_tag_name(name) {
+        Some(parent) => Ok(Some(state.store.find_tag_info(parent).await?)),
+        None => Ok(None),
+    }
+}
+
 /// Resource tag. For files resources on MacOS/iOS that roughly translates to file / directory tag.
 // For web resources that roughly translates to bookmark tags.
 #[juniper::graphql_object(Context = State)]
@@ -52,6 +103,74 @@ impl Tag {
     fn target(&self) -> Resource {
         Resource::from(&self.target_url)
     }
+
+    /// Tags that are applied to the same resources as this tag, most common
+    /// first.
+    #[graphql(arguments(first(default = 10)))]
+    async fn related(&self, state: &State, first: i32) -> FieldResult<Vec<TagInfo>> {
+        find_related_tags(state, &self.name, first).await
+    }
+
+    /// Tag this tag is nested under, e.g. `project/ksp` for the
+    /// `project/ksp/design` tag.
+    async fn parent(&self, state: &State) -> FieldResult<Option<TagInfo>> {
+        find_parent_tag(state, &self.name).await
+    }
+
+    /// Tags nested directly under this tag.
+    async fn children(&self, state: &State) -> FieldResult<Vec<TagInfo>> {
+        state.store.find_child_tags(&self.name).await
+    }
+}
+
+/// Distinct tag name, which can be used to build tag browsers and tag clouds.
+#[juniper::graphql_object(Context = State)]
+impl TagInfo {
+    /// tag name
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of resources tagged by this tag. When listed as a related tag it
+    /// is the number of resources that have both tags.
+    fn count(&self) -> i32 {
+        self.count as i32
+    }
+
+    /// Tags that are applied to the same resources as this tag, most common
+    /// first.
+    #[graphql(arguments(first(default = 10)))]
+    async fn related(&self, state: &State, first: i32) -> FieldResult<Vec<TagInfo>> {
+        find_related_tags(state, &self.name, first).await
+    }
+
+    /// Tag this tag is nested under, e.g. `project/ksp` for the
+    /// `project/ksp/design` tag.
+    async fn parent(&self, state: &State) -> FieldResult<Option<TagInfo>> {
+        find_parent_tag(state, &self.name).await
+    }
+
+    /// Tags nested directly under this tag.
+    async fn children(&self, state: &State) -> FieldResult<Vec<TagInfo>> {
+        state.store.find_child_tags(&self.name).await
+    }
+
+    /// Occurrences of this tag. If `descendants` is set occurrences of the
+    /// tags nested under it are included as well.
+    #[graphql(arguments(descendants(default = false)))]
+    async fn tags(
+        &self,
+        state: &State,
+        first: Option<i32>,
+        after: Option<String>,
+        order_by: Option<TagOrder>,
+        descendants: bool,
+    ) -> FieldResult<TagConnection> {
+        let order = order_by.unwrap_or(TagOrder::TargetTitle);
+        let window = Window::new(first, after)?;
+        let tags = find_tags(state, &self.name, descendants, order, window).await?;
+        Ok(TagConnection::from(tags))
+    }
 }
 
 /// Represents an inline link in markdown file.
@@ -91,6 +210,10 @@ impl Link {
                 description: self.referrer_description.clone(),
                 icon: self.referrer_icon.clone(),
                 image: self.referrer_image.clone(),
+                created_at: self.referrer_created_at,
+                modified_at: self.referrer_modified_at,
+                ingested_at: self.referrer_ingested_at,
+                language: self.referrer_language.clone(),
             }),
         }
     }
@@ -104,6 +227,68 @@ impl Link {
     }
 }
 
+/// Edge in the connection of links.
+#[juniper::graphql_object(Context = State)]
+impl LinkEdge {
+    /// Opaque cursor identifying position of this link in the connection.
+    fn cursor(&self) -> &str {
+        &self.cursor
+    }
+    fn node(&self) -> Link {
+        self.node.clone()
+    }
+}
+
+/// Page of links in a relay style connection.
+#[juniper::graphql_object(Context = State)]
+impl LinkConnection {
+    fn edges(&self) -> Vec<LinkEdge> {
+        self.edges.clone()
+    }
+    /// Links of this page, without cursors.
+    fn nodes(&self) -> Vec<Link> {
+        self.edges.iter().map(|edge| edge.node.clone()).collect()
+    }
+    fn page_info(&self) -> PageInfo {
+        self.page_info.clone()
+    }
+    /// Total number of links in the connection across all pages.
+    fn total_count(&self) -> i32 {
+        self.total_count as i32
+    }
+}
+
+/// Edge in the connection of tags.
+#[juniper::graphql_object(Context = State)]
+impl TagEdge {
+    /// Opaque cursor identifying position of this tag in the connection.
+    fn cursor(&self) -> &str {
+        &self.cursor
+    }
+    fn node(&self) -> Tag {
+        self.node.clone()
+    }
+}
+
+/// Page of tags in a relay style connection.
+#[juniper::graphql_object(Context = State)]
+impl TagConnection {
+    fn edges(&self) -> Vec<TagEdge> {
+        self.edges.clone()
+    }
+    /// Tags of this page, without cursors.
+    fn nodes(&self) -> Vec<Tag> {
+        self.edges.iter().map(|edge| edge.node.clone()).collect()
+    }
+    fn page_info(&self) -> PageInfo {
+        self.page_info.clone()
+    }
+    /// Total number of tags in the connection across all pages.
+    fn total_count(&self) -> i32 {
+        self.total_count as i32
+    }
+}
+
 #[juniper::graphql_object(Context = State)]
 impl Resource {
     /// URL of the resource
@@ -125,33 +310,138 @@ impl Resource {
                     cid: None,
                     icon: None,
                     image: None,
+                    created_at: None,
+                    modified_at: None,
+                    ingested_at: None,
+                    language: None,
                 }
             }
         }
     }
 
     /// Resources this document links to.
-    async fn links(&self, state: &State) -> FieldResult<Vec<Link>> {
-        state.store.find_links_by_referrer(&self.url).await
+    async fn links(
+        &self,
+        state: &State,
+        first: Option<i32>,
+        after: Option<String>,
+        order_by: Option<LinkOrder>,
+    ) -> FieldResult<LinkConnection> {
+        let order = order_by.unwrap_or(LinkOrder::TargetUrl);
+        let window = Window::new(first, after)?;
+        let links = state
+            .store
+            .find_links_by_referrer(&self.url, order, window)
+            .await?;
+        Ok(LinkConnection::from(links))
+    }
+
+    /// Revisions of this document, latest first.
+    async fn revisions(&self, state: &State, first: Option<i32>) -> FieldResult<Vec<Revision>> {
+        let mut revisions = state.store.select_revisions(&self.url)?;
+        if let Some(first) = first {
+            revisions.truncate(first.max(0) as usize);
+        }
+        Ok(revisions)
     }
 
     /// Resources that link to this document.
-    async fn backLinks(&self, state: &State) -> FieldResult<Vec<Link>> {
-        state.store.find_links_by_target(&self.url).await
+    async fn backLinks(
+        &self,
+        state: &State,
+        first: Option<i32>,
+        after: Option<String>,
+        order_by: Option<LinkOrder>,
+    ) -> FieldResult<LinkConnection> {
+        let order = order_by.unwrap_or(LinkOrder::ReferrerTitle);
+        let window = Window::new(first, after)?;
+        let links = state
+            .store
+            .find_links_by_target(&self.url, order, window)
+            .await?;
+        Ok(LinkConnection::from(links))
     }
 
-    /// Tag associated to this document.
-    async fn tags(&self, state: &State) -> FieldResult<Vec<Tag>> {
-        state.store.find_tags_by_target(&self.url).await
+    /// Resources within `depth` links (at most 5) from this document along
+    /// with the links between them. Links are followed in both directions
+    /// unless `direction` is specified and can be limited to the given `kinds`.
+    #[graphql(arguments(depth(default = 1)))]
+    async fn neighborhood(
+        &self,
+        state: &State,
+        depth: i32,
+        direction: Option<LinkDirection>,
+        kinds: Option<Vec<LinkKind>>,
+    ) -> FieldResult<Graph> {
+        graph::neighborhood(
+            &state.store,
+            &self.url,
+            depth.max(0) as usize,
+            direction.unwrap_or(LinkDirection::Both),
+            &kinds.unwrap_or_default(),
+        )
     }
 
-    // Resources similar to this one.
-    #[graphql(arguments(first(default = 5)))]
-    async fn similar(&self, _first: i32, _state: &State) -> Vec<SimilarResource> {
-        // let index = &state.index;
-        // index.search_similar(input, 10);
+    /// Tag associated to this document.
+    async fn tags(
+        &self,
+        state: &State,
+        first: Option<i32>,
+        after: Option<String>,
+        order_by: Option<TagOrder>,
+    ) -> FieldResult<TagConnection> {
+        let order = order_by.unwrap_or(TagOrder::Name);
+        let window = Window::new(first, after)?;
+        let tags = state
+            .store
+            .find_tags_by_target(&self.url, order, window)
+            .await?;
+        Ok(TagConnection::from(tags))
+    }
 
-        vec![]
+    /// Resources similar to this one, identified by the `keywords` most
+    /// relevant terms of its indexed content and by its tags. Weights control
+    /// how much keyword matches in the content and title of other resources,
+    /// and shared tags contribute to the similarity. By default only content
+    /// matches are considered. Resources that were not indexed have no similar
+    /// resources. Optional filter restricts which resources are considered.
+    #[graphql(arguments(
+        first(default = 5),
+        keywords(default = 10),
+        body_weight(default = 1.0),
+        title_weight(default = 0.0),
+        tag_weight(default = 0.0)
+    ))]
+    async fn similar(
+        &self,
+        state: &State,
+        first: i32,
+        keywords: i32,
+        body_weight: f64,
+        title_weight: f64,
+        tag_weight: f64,
+        filter: Option<InputFilter>,
+    ) -> FieldResult<Vec<SimilarResource>> {
+        let index = &state.index;
+        let weights = SimilarityWeights {
+            body: body_weight as f32,
+            title: title_weight as f32,
+            tags: tag_weight as f32,
+        };
+        match index.find_document(&self.url)? {
+            Some(document) => {
+                let keywords = index.extract_keywords(&document.body, keywords.max(0) as usize);
+                Ok(index.search_with_keywords(
+                    &self.url,
+                    &keywords,
+                    &document.tags,
+                    &weights,
+                    &filter.unwrap_or_default(),
+                    first.max(0) as usize,
+                )?)
+            }
+            None => Ok(vec![]),
+        }
     }
 }
 
@@ -168,9 +458,100 @@ impl SimilarResources {
     /// Similar resources.
     #[graphql(arguments(first(default = 5)))]
     fn similar(&self, first: i32, state: &State) -> FieldResult<Vec<SimilarResource>> {
-        Ok(state
-            .index
-            .search_with_keywords(&self.source_url, &self.keywords, first as usize)?)
+        Ok(state.index.search_with_keywords(
+            &self.source_url,
+            &self.keywords,
+            &self.tags,
+            &self.weights,
+            &self.filter,
+            first.max(0) as usize,
+        )?)
+    }
+    /// Breakdown of all the similar resources by tags and URLs.
+    fn facets(&self, state: &State) -> FieldResult<Facets> {
+        Ok(state.index.similar_facets(
+            &self.source_url,
+            &self.keywords,
+            &self.tags,
+            &self.weights,
+            &self.filter,
+        )?)
+    }
+}
+
+/// Resource in the link graph.
+#[juniper::graphql_object(Context = State)]
+impl GraphNode {
+    fn resource(&self) -> Resource {
+        Resource::from(&self.url)
+    }
+    /// Number of links between this resource and the one graph was built
+    /// from.
+    fn depth(&self) -> i32 {
+        self.depth as i32
+    }
+}
+
+/// Part of the link graph.
+#[juniper::graphql_object(Context = State)]
+impl Graph {
+    fn nodes(&self) -> Vec<GraphNode> {
+        self.nodes.clone()
+    }
+    fn edges(&self) -> Vec<GraphEdge> {
+        self.edges.clone()
+    }
+}
+
+/// Resource matching the full-text search query.
+#[juniper::graphql_object(Context = State)]
+impl SearchResult {
+    /// Matched resource.
+    fn resource(&self) -> Resource {
+        Resource::from(&self.url)
+    }
+    /// Relevance score of the match, higher is better.
+    fn score(&self) -> f64 {
+        self.score as f64
+    }
+    /// HTML fragment of the content with matched terms wrapped in `<b>` tags.
+    fn snippet(&self) -> Option<String> {
+        self.snippet.clone()
+    }
+}
+
+/// Edge in the connection of search results.
+#[juniper::graphql_object(Context = State)]
+impl SearchEdge {
+    /// Opaque cursor identifying position of this result in the connection.
+    fn cursor(&self) -> &str {
+        &self.cursor
+    }
+    fn node(&self) -> SearchResult {
+        self.node.clone()
+    }
+}
+
+/// Page of search results in a relay style connection.
+#[juniper::graphql_object(Context = State)]
+impl SearchConnection {
+    fn edges(&self) -> Vec<SearchEdge> {
+        self.edges.clone()
+    }
+    /// Search results of this page, without cursors.
+    fn nodes(&self) -> Vec<SearchResult> {
+        self.edges.iter().map(|edge| edge.node.clone()).collect()
+    }
+    fn page_info(&self) -> PageInfo {
+        self.page_info.clone()
+    }
+    /// Total number of matching resources across all pages.
+    fn total_count(&self) -> i32 {
+        self.total_count as i32
+    }
+    /// Breakdown of all the matching resources by tags and URLs.
+    fn facets(&self) -> Facets {
+        self.facets.clone()
     }
 }
 
@@ -192,42 +573,307 @@ impl Query {
     async fn resource(_state: &State, url: String) -> Resource {
         Resource::from(url)
     }
-    /// finds tags for the given name.
-    async fn tags(state: &State, name: String) -> FieldResult<Vec<Tag>> {
-        state.store.find_tags_by_name(&name).await
+    /// finds resources with the given content identifier, that is resources
+    /// with identical content under different URLs.
+    async fn resource_by_cid(state: &State, cid: String) -> FieldResult<Vec<Resource>> {
+        state.store.select_resources_by_cid(&cid)
+    }
+    /// finds tags for the given name. If `descendants` is set tags nested
+    /// under it are included, e.g. `project/ksp/design` for `project/ksp`.
+    #[graphql(arguments(descendants(default = false)))]
+    async fn tags(
+        state: &State,
+        name: String,
+        first: Option<i32>,
+        after: Option<String>,
+        order_by: Option<TagOrder>,
+        descendants: bool,
+    ) -> FieldResult<TagConnection> {
+        let order = order_by.unwrap_or(TagOrder::TargetTitle);
+        let window = Window::new(first, after)?;
+        let tags = find_tags(state, &name, descendants, order, window).await?;
+        Ok(TagConnection::from(tags))
+    }
+    /// lists all distinct tags along with the number of resources they are
+    /// applied to, most used tags first.
+    async fn all_tags(state: &State, first: Option<i32>) -> FieldResult<Vec<TagInfo>> {
+        state
+            .store
+            .select_all_tags(first.map(|first| first.max(0) as usize))
     }
 
-    #[graphql(arguments(first(default = 5)))]
-    async fn similar(state: &State, input: InputSimilar, first: i32) -> SimilarResources {
-        let keywords = state.index.extract_keywords(&input.content, first as usize);
-        SimilarResources {
+    /// finds shortest path of at most `maxLength` links from one resource to
+    /// the other. Links are followed from referrer to target unless another
+    /// `direction` is specified and can be limited to the given `kinds`.
+    #[graphql(arguments(max_length(default = 10)))]
+    async fn path(
+        state: &State,
+        from: String,
+        to: String,
+        max_length: i32,
+        direction: Option<LinkDirection>,
+        kinds: Option<Vec<LinkKind>>,
+    ) -> FieldResult<Option<Graph>> {
+        graph::path(
+            &state.store,
+            &from,
+            &to,
+            max_length.max(0) as usize,
+            direction.unwrap_or(LinkDirection::Outgoing),
+            &kinds.unwrap_or_default(),
+        )
+    }
+    /// lists resources modified within the given time range, most recently
+    /// modified first. Both `since` and `until` are optional, `since` is
+    /// inclusive while `until` is exclusive.
+    async fn recent(
+        state: &State,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        first: Option<i32>,
+    ) -> FieldResult<Vec<Resource>> {
+        state.store.select_recent_resources(
+            since.as_ref(),
+            until.as_ref(),
+            first.map(|first| first.max(0) as usize),
+        )
+    }
+
+    /// lists links to resources that were never ingested.
+    async fn dangling_links(
+        state: &State,
+        first: Option<i32>,
+        after: Option<String>,
+        order_by: Option<LinkOrder>,
+    ) -> FieldResult<LinkConnection> {
+        let order = order_by.unwrap_or(LinkOrder::ReferrerTitle);
+        let window = Window::new(first, after)?;
+        let page = state.store.select_dangling_links(order, window)?;
+        Ok(LinkConnection::from(page))
+    }
+    /// lists links to local files that no longer exist.
+    async fn missing_file_links(
+        state: &State,
+        first: Option<i32>,
+        after: Option<String>,
+        order_by: Option<LinkOrder>,
+    ) -> FieldResult<LinkConnection> {
+        let order = order_by.unwrap_or(LinkOrder::ReferrerTitle);
+        let links = state.store.select_missing_file_links(order).await?;
+        // Files are checked after the links are selected, so the page can
+        // only be sliced out of the checked list.
+        Ok(LinkConnection::from(Page::new(links, first, after)?))
+    }
+    /// lists resources that neither link to nor are linked from any other
+    /// resource, ordered by title.
+    async fn orphans(state: &State, first: Option<i32>) -> FieldResult<Vec<Resource>> {
+        state
+            .store
+            .select_orphan_resources(first.map(|first| first.max(0) as usize))
+    }
+
+    /// full-text search through titles and contents of the resources, best
+    /// matches first. Query supports Tantivy query syntax, e.g.
+    /// `title:rust AND (async OR await)`. Optional filter restricts which
+    /// resources are searched.
+    #[graphql(arguments(first(default = 10)))]
+    async fn search(
+        state: &State,
+        query: String,
+        first: i32,
+        after: Option<String>,
+        filter: Option<InputFilter>,
+    ) -> FieldResult<SearchConnection> {
+        let offset = start_offset(after)?;
+        let filter = filter.unwrap_or_default();
+        let (results, total_count, facets) =
+            state
+                .index
+                .search(&query, &filter, offset, first.max(0) as usize)?;
+        let page = Page::from_window(results, offset, total_count);
+        Ok(SearchConnection {
+            facets,
+            ..SearchConnection::from(page)
+        })
+    }
+
+    /// finds resources similar to the given content, identified by the
+    /// `keywords` most relevant terms of it. Weights control how much keyword
+    /// matches in the content and title of other resources, and shared tags
+    /// contribute to the similarity, by default only content matches are
+    /// considered. Optional filter restricts which resources are considered.
+    #[graphql(arguments(
+        keywords(default = 10),
+        body_weight(default = 1.0),
+        title_weight(default = 0.0),
+        tag_weight(default = 0.0)
+    ))]
+    async fn similar(
+        state: &State,
+        input: InputSimilar,
+        keywords: i32,
+        body_weight: f64,
+        title_weight: f64,
+        tag_weight: f64,
+        filter: Option<InputFilter>,
+    ) -> FieldResult<SimilarResources> {
+        let keywords = state
+            .index
+            .extract_keywords(&input.content, keywords.max(0) as usize);
+        let source_url = input.url.unwrap_or(String::from(""));
+        let tags = match input.tags {
+            Some(tags) => tags,
+            None => state
+                .index
+                .find_document(&source_url)?
+                .map(|document| document.tags)
+                .unwrap_or_default(),
+        };
+        Ok(SimilarResources {
             keywords,
-            source_url: input.url.unwrap_or(String::from("")),
-        }
+            source_url,
+            tags,
+            weights: SimilarityWeights {
+                body: body_weight as f32,
+                title: title_weight as f32,
+                tags: tag_weight as f32,
+            },
+            filter: filter.unwrap_or_default(),
+        })
     }
 }
 
 impl Mutations {
     /// Injests resource into knowledge base.
-    pub async fn ingest(state: &State, input: InputResource) -> FieldResult<Resource> {
+    pub async fn ingest(state: &State, mut input: InputResource) -> FieldResult<Resource> {
         log::info!("Ingesting resource {:}", input.url);
-        let resource = state.store.insert_resource(&input)?;
-
-        if let Some(tags) = input.tags {
-            state.store.insert_tags(&input.url, &tags)?;
+        // Content is stored under its identifier and index is rebuilt from it,
+        // so identifier is always derived from the content. Identifier
+        // provided along with the content must match it.
+        if let Some(content) = input.content.as_ref() {
+            let cid = content::cid(content.as_bytes());
+            if let Some(given) = input.cid.as_ref().filter(|given| **given != cid) {
+                return Err(FieldError::from(format!(
+                    "Content identifier {:} of {:} does not match its content, which has {:}",
+                    given, input.url, cid
+                )));
+            }
+            input.cid = Some(cid);
         }
-        if let Some(links) = input.links {
-            state.store.insert_links(&input.url, &links)?;
+        if input.language.is_none() {
+            input.language = input
+                .content
+                .as_ref()
+                .and_then(|content| Language::detect(content))
+                .map(|language| language.code().to_string());
         }
+        // Resource, tags and links are committed together, and only once
+        // they are, document is staged in the index. That way failure never
+        // leaves an index entry for a resource that is not in the store. If
+        // indexing fails resource is stored but not indexed, which `check
+        // --repair` fixes, so error says as much.
+        let resource = state.store.ingest(&input)?;
 
+        let index = &state.index;
         if let Some(content) = input.content {
-            let index = &state.index;
-            index.ingest(&input.url, &input.title, &content).await?;
+            let language = input.language.as_ref().map(String::as_str);
+            let tags: Vec<String> = input
+                .tags
+                .iter()
+                .flatten()
+                .map(|tag| tag.name.clone())
+                .collect();
+            index
+                .ingest(&input.url, &input.title, &content, language, &tags)
+                .await
+                .map_err(|error| {
+                    log::error!("Resource {:} was stored but not indexed", input.url);
+                    FieldError::from(format!(
+                        "Resource {:} was stored but failed to be indexed ({:}), run `check --repair` to index it",
+                        input.url, error
+                    ))
+                })?;
+        } else {
+            // Resource without content can't be searched for, so the index
+            // entry of its earlier version would be stale.
+            index.forget(&input.url).await?;
         }
         log::info!("Resource was ingested {:}", input.url);
 
         Ok(resource)
     }
+    /// Removes resource, its links, tags and full-text index entry from the
+    /// knowledge base. Returns `false` if resource was not in the knowledge base.
+    ///
+    /// Index entry is removed first, so that failure never leaves a resource
+    /// that is gone from the store in the search results. If removing it from
+    /// the store fails, resource is left stored without an index entry, which
+    /// `check --repair` fixes, so error says as much.
+    pub async fn forget(state: &State, url: String) -> FieldResult<bool> {
+        log::info!("Forgetting resource {:}", url);
+        state.index.forget(&url).await?;
+        let deleted = state.store.delete_resource(&url).map_err(|error| {
+            log::error!("Resource {:} was removed from index but not from store", url);
+            FieldError::from(format!(
+                "Resource {:} was removed from index but failed to be removed from store ({:}), run `check --repair` to index it again",
+                url, error.message()
+            ))
+        })?;
+        log::info!("Resource was forgotten {:}", url);
+
+        Ok(deleted)
+    }
+    /// Rebuilds the full-text index from the contents stored in the database.
+    /// Calls `progress` with the number of processed resources, total number
+    /// of resources and the URL of the resource after each one. Index entries
+    /// are only replaced for resources with stored content, the rest are kept
+    /// as they are.
+    pub async fn reindex<F>(state: &State, mut progress: F) -> FieldResult<ReindexReport>
+    where
+        F: FnMut(usize, usize, &str),
+    {
+        let urls = state.store.select_resource_urls()?;
+        let total = urls.len();
+        log::info!("Reindexing {:} resources", total);
+        let index = &state.index;
+
+        let mut report = ReindexReport {
+            indexed: 0,
+            skipped: 0,
+            skipped_urls: vec![],
+        };
+        for (n, url) in urls.iter().enumerate() {
+            match state.store.select_indexable_resource(url)? {
+                Some(IndexableResource {
+                    url,
+                    title,
+                    language,
+                    content: Some(content),
+                    tags,
+                }) => {
+                    let language = language.as_ref().map(String::as_str);
+                    index
+                        .ingest(&url, &title, &content, language, &tags)
+                        .await?;
+                    report.indexed += 1;
+                }
+                _ => {
+                    log::warn!("No content is stored for {:}, skipping", url);
+                    report.skipped += 1;
+                    report.skipped_urls.push(url.clone());
+                }
+            }
+            progress(n + 1, total, url);
+        }
+        index.commit().await?;
+        log::info!(
+            "Reindexed {:} resources, skipped {:}",
+            report.indexed,
+            report.skipped
+        );
+
+        Ok(report)
+    }
 }
 
 #[juniper::graphql_object(Context = State)]
@@ -235,6 +881,27 @@ impl Mutations {
     async fn ingest(state: &State, resource: InputResource) -> FieldResult<Resource> {
         Mutations::ingest(state, resource).await
     }
+    async fn forget(state: &State, url: String) -> FieldResult<bool> {
+        Mutations::forget(state, url).await
+    }
+    /// Rebuilds the full-text index from the contents stored in the database.
+    async fn reindex(state: &State) -> FieldResult<ReindexReport> {
+        Mutations::reindex(state, |done, total, url| {
+            log::info!("Reindexed {:}/{:} {:}", done, total, url)
+        })
+        .await
+    }
+    /// Takes a consistent copy of the database and the committed full-text
+    /// index into the `snapshots/$name` subdirectory of the data directory.
+    /// Name defaults to the current time.
+    async fn snapshot(state: &State, name: Option<String>) -> FieldResult<Snapshot> {
+        let config = state.config.as_ref().ok_or_else(|| {
+            FieldError::from("Snapshot of the in-memory knowledge base can not be taken")
+        })?;
+        let name = name.unwrap_or_else(|| Utc::now().format("%Y-%m-%dT%H-%M-%S").to_string());
+        let dest = config.snapshot_path(&name)?;
+        backup::snapshot(&state.store, &state.index, &dest).await
+    }
     async fn open(_state: &State, url: String) -> Open {
         log::info!("Opening a resource {:}", url);
         if let Ok(status) = open::that(url) {
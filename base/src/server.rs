@@ -1,8 +1,12 @@
+use crate::config::Config;
+use crate::graph;
 use crate::service::Service;
+use async_std::task;
 use async_trait::async_trait;
 use futures::future::BoxFuture;
 use juniper::http::GraphQLRequest;
 use log;
+use serde::Deserialize;
 use std::sync::RwLock;
 use tide::{Middleware, Next, Request, Response, Server};
 
@@ -52,6 +56,42 @@ async fn handle_graphql(mut request: Request<State>) -> Response {
     response
 }
 
+#[derive(Deserialize)]
+struct GraphQuery {
+    format: Option<String>,
+}
+
+/// Responds with the whole link graph in the format given by the `format`
+/// query parameter (`dot`, `gexf` or `graphml`), which defaults to `graphml`.
+async fn handle_graph(request: Request<State>) -> Response {
+    let format = match request.query::<GraphQuery>() {
+        Ok(GraphQuery {
+            format: Some(format),
+        }) => format.parse(),
+        _ => Ok(graph::Format::GraphML),
+    };
+    let format = match format {
+        Ok(format) => format,
+        Err(error) => return Response::new(400).body_string(format!("{}", error)),
+    };
+
+    log::info!("Exporting link graph as {:?}", format);
+    // Export reads the whole graph, so it is run on a blocking task to not
+    // hold up the executor.
+    let store = request.state().store.clone();
+    let result = task::spawn_blocking(move || {
+        let mut body = Vec::new();
+        graph::export(&store, format, &mut body).map(|()| body)
+    })
+    .await;
+    match result {
+        Ok(body) => Response::new(200)
+            .body_string(String::from_utf8_lossy(&body).into_owned())
+            .set_header("content-type", format.content_type()),
+        Err(error) => Response::new(500).body_string(error.message().to_string()),
+    }
+}
+
 async fn handle_root_head(_request: Request<State>) -> Response {
     Response::new(200)
 }
@@ -101,13 +141,13 @@ impl<State: Send + Sync + 'static> Middleware<State> for Headers {
     }
 }
 
-pub async fn activate(address: &str) -> std::io::Result<()> {
+pub async fn activate(address: &str, config: &Config) -> std::io::Result<()> {
     // let schema = Schema::new();
     // let state = State {
     //     schema,
     //     state: SchemaState::new()?,
     // };
-    let state = Service::new()?;
+    let state = Service::new(config)?;
     let headers = Headers::new().set("Server", "Knowledge-Server");
     let mut server = Server::with_state(state);
     server.middleware(headers);
@@ -115,6 +155,7 @@ pub async fn activate(address: &str) -> std::io::Result<()> {
     server.at("/").head(handle_root_head);
     server.at("/graphql").post(handle_graphql);
     server.at("/graphiql").get(handle_graphiql);
+    server.at("/graph").get(handle_graph);
 
     server.listen(address).await
 }
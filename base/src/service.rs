@@ -1,9 +1,15 @@
-use crate::data::InputResource;
+use crate::check;
+use crate::config::Config;
+use crate::data::{InputResource, ReindexReport};
+use crate::graph;
+use crate::index;
 use crate::index::IndexService;
 use crate::schema::{Mutations, Schema, State};
 use crate::store::DataStore;
 use juniper::http::{GraphQLRequest, GraphQLResponse};
+use serde_json;
 use std::io;
+use std::io::{BufRead, Write};
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -11,27 +17,70 @@ pub struct Service {
     pub schema: Schema,
     pub store: DataStore,
     pub index: Arc<IndexService>,
+    /// Configuration service was created with, `None` for in-memory service.
+    pub config: Option<Config>,
 }
 impl Service {
-    pub fn new() -> io::Result<Self> {
-        let store = DataStore::open()?;
-        let index = Arc::new(IndexService::open().unwrap());
+    pub fn new(config: &Config) -> io::Result<Self> {
+        let index = match IndexService::open(&config.index_path()) {
+            Ok(index) => index,
+            Err(error @ index::Error::OutdatedSchema(_)) => {
+                return Err(outdated_index(config, error))
+            }
+            Err(error) => return Err(io::Error::new(io::ErrorKind::Other, format!("{}", error))),
+        };
+        Service::with_index(config, index)
+    }
+    /// Creates service with an empty index in place of the existing one, which
+    /// is discarded. Useful when index can not be opened because it is
+    /// corrupted or has an outdated schema, so that it can be rebuilt with
+    /// `reindex`.
+    pub fn with_new_index(config: &Config) -> io::Result<Self> {
+        let index = IndexService::create(&config.index_path())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?;
+        Service::with_index(config, index)
+    }
+    fn with_index(config: &Config, index: IndexService) -> io::Result<Self> {
+        let store = DataStore::open(&config.store_path())?;
+        let schema = Schema::new();
+
+        Ok(Service {
+            index: Arc::new(index),
+            schema,
+            store,
+            config: Some(config.clone()),
+        })
+    }
+    /// Creates ephemeral service backed by in-memory database and index. It
+    /// does not touch user's knowledge base and all of its data is gone once
+    /// service is dropped, which makes it useful for tests and previews.
+    pub fn in_memory() -> io::Result<Self> {
+        let store = DataStore::in_memory()?;
+        let index = Arc::new(
+            IndexService::in_memory()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?,
+        );
         let schema = Schema::new();
 
         Ok(Service {
             index,
             schema,
             store,
+            config: None,
         })
     }
+    fn state(&self) -> State {
+        State {
+            store: self.store.clone(),
+            index: self.index.clone(),
+            config: self.config.clone(),
+        }
+    }
     pub async fn execute<B, F>(&self, request: GraphQLRequest, f: F) -> B
     where
         F: Fn(GraphQLResponse<'_>) -> B,
     {
-        let state = State {
-            store: self.store.clone(),
-            index: self.index.clone(),
-        };
+        let state = self.state();
         let root = &self.schema.root;
         let response: GraphQLResponse<'_> = request.execute_async(root, &state).await;
         // TODO: Fix no unwrap belongs here.
@@ -39,10 +88,7 @@ impl Service {
         f(response)
     }
     pub async fn ingest(&self, input: InputResource) -> io::Result<()> {
-        let state = State {
-            store: self.store.clone(),
-            index: self.index.clone(),
-        };
+        let state = self.state();
 
         Mutations::ingest(&state, input)
             .await
@@ -50,6 +96,65 @@ impl Service {
 
         Ok(())
     }
+    /// Removes resource from the knowledge base and commits the change to the
+    /// index so that it no longer shows up in search results.
+    pub async fn forget(&self, url: &str) -> io::Result<bool> {
+        let state = self.state();
+
+        let deleted = Mutations::forget(&state, url.to_string())
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.message()))?;
+        self.commit().await?;
+
+        Ok(deleted)
+    }
+    /// Rebuilds the full-text index from the contents stored in the database,
+    /// calling `progress` after each resource. Index entries of resources
+    /// without stored content are left as they are.
+    pub async fn reindex<F>(&self, progress: F) -> io::Result<ReindexReport>
+    where
+        F: FnMut(usize, usize, &str),
+    {
+        let state = self.state();
+
+        Mutations::reindex(&state, progress)
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.message()))
+    }
+    /// Reports discrepancies between the store and the index, repairing them
+    /// if `repair` is set.
+    pub async fn check(&self, repair: bool) -> io::Result<check::Report> {
+        check::check(&self.store, &self.index, repair)
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.message()))
+    }
+    /// Writes every stored resource to `writer` as newline-delimited JSON in
+    /// the shape of `InputResource`, returning the number of resources written.
+    pub fn export<W: Write>(&self, writer: W) -> io::Result<usize> {
+        export_store(&self.store, writer)
+    }
+    /// Ingests resources from the newline-delimited JSON produced by `export`,
+    /// returning the number of resources imported.
+    pub async fn import<R: BufRead>(&self, reader: R) -> io::Result<usize> {
+        let mut count = 0;
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let input: InputResource = serde_json::from_str(&line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid resource on line {:}: {:}", n + 1, e),
+                )
+            })?;
+            self.ingest(input).await?;
+            count += 1;
+        }
+        self.commit().await?;
+
+        Ok(count)
+    }
     pub async fn commit(&self) -> io::Result<()> {
         self.index
             .commit()
@@ -58,3 +163,242 @@ impl Service {
         Ok(())
     }
 }
+
+/// Writes every resource of the knowledge base described by `config` to
+/// `writer`, same as `Service::export` does. Only the database is opened, so
+/// it works while server is running.
+pub fn export<W: Write>(config: &Config, writer: W) -> io::Result<usize> {
+    export_store(&open_store(config)?, writer)
+}
+
+/// Writes the whole link graph of the knowledge base described by `config` to
+/// `writer` in the given format. Like `export` it only opens the database.
+pub fn export_graph<W: Write>(
+    config: &Config,
+    format: graph::Format,
+    mut writer: W,
+) -> io::Result<()> {
+    graph::export(&open_store(config)?, format, &mut writer)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.message()))
+}
+
+/// Opens the database of the existing knowledge base described by `config`
+/// without the index, which is locked while server is running.
+fn open_store(config: &Config) -> io::Result<DataStore> {
+    let path = config.store_path();
+    if !path.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No knowledge base found at {:?}", config.data_dir()),
+        ));
+    }
+    DataStore::open(&path)
+}
+
+fn export_store<W: Write>(store: &DataStore, mut writer: W) -> io::Result<usize> {
+    let urls = store
+        .select_resource_urls()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.message()))?;
+
+    let mut count = 0;
+    for url in urls {
+        let resource = store
+            .select_input_resource(&url)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.message()))?;
+        if let Some(resource) = resource {
+            serde_json::to_writer(&mut writer, &resource)?;
+            writer.write_all(b"\n")?;
+            count += 1;
+        }
+    }
+    writer.flush()?;
+
+    Ok(count)
+}
+
+/// Error for the index that has to be rebuilt because of the schema change.
+/// Resources ingested before their content was stored can't be reindexed,
+/// so if there are any, user is told to scan them again.
+fn outdated_index(config: &Config, error: index::Error) -> io::Error {
+    let unstored = DataStore::open(&config.store_path())
+        .ok()
+        .and_then(|store| store.select_content_resource_urls().ok())
+        .map(|urls| urls.iter().filter(|(_, stored)| !stored).count())
+        .unwrap_or(0);
+    let message = if unstored > 0 {
+        format!(
+            "{}. Content of {} resources is not stored, so they need to be scanned again once index is rebuilt",
+            error, unstored
+        )
+    } else {
+        format!("{}", error)
+    };
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content;
+    use crate::testing::resource;
+
+    #[async_std::test]
+    async fn in_memory_services_are_isolated() {
+        let service = Service::in_memory().unwrap();
+        let other = Service::in_memory().unwrap();
+        service
+            .ingest(InputResource {
+                content: Some("Notes kept in memory".to_string()),
+                ..resource("file:///a.md", &[], &[])
+            })
+            .await
+            .unwrap();
+        service.commit().await.unwrap();
+
+        assert_eq!(service.store.select_resource_urls().unwrap().len(), 1);
+        assert!(other.store.select_resource_urls().unwrap().is_empty());
+        assert!(other.index.indexed_urls().unwrap().is_empty());
+    }
+
+    #[async_std::test]
+    async fn reingest_without_content_drops_index_entry() {
+        let service = Service::in_memory().unwrap();
+        service
+            .ingest(InputResource {
+                content: Some("Notes about graphs".to_string()),
+                ..resource("file:///a.md", &[], &[])
+            })
+            .await
+            .unwrap();
+        service.commit().await.unwrap();
+        assert_eq!(service.index.indexed_urls().unwrap(), vec!["file:///a.md"]);
+
+        service
+            .ingest(resource("file:///a.md", &[], &[]))
+            .await
+            .unwrap();
+        service.commit().await.unwrap();
+        assert!(service.index.indexed_urls().unwrap().is_empty());
+        assert!(service.check(false).await.unwrap().is_consistent());
+    }
+
+    #[async_std::test]
+    async fn reindex_skips_resources_without_content() {
+        let service = Service::in_memory().unwrap();
+        service
+            .ingest(InputResource {
+                content: Some("Walking the graph of notes".to_string()),
+                ..resource("file:///a.md", &[], &[])
+            })
+            .await
+            .unwrap();
+        service
+            .ingest(resource("file:///b.md", &[], &[]))
+            .await
+            .unwrap();
+        service.commit().await.unwrap();
+
+        let report = service.reindex(|_, _, _| {}).await.unwrap();
+        assert_eq!(report.indexed, 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.skipped_urls, vec!["file:///b.md".to_string()]);
+        assert_eq!(service.index.indexed_urls().unwrap(), vec!["file:///a.md"]);
+    }
+
+    #[async_std::test]
+    async fn content_is_stored_under_its_cid() {
+        let service = Service::in_memory().unwrap();
+        let content = "Walking the graph of notes";
+        let result = service
+            .ingest(InputResource {
+                cid: Some(content::cid(b"Something else")),
+                content: Some(content.to_string()),
+                ..resource("file:///a.md", &[], &[])
+            })
+            .await;
+        assert!(result.is_err());
+        assert!(service.store.select_resource_urls().unwrap().is_empty());
+
+        service
+            .ingest(InputResource {
+                cid: Some(content::cid(content.as_bytes())),
+                content: Some(content.to_string()),
+                ..resource("file:///a.md", &[], &[])
+            })
+            .await
+            .unwrap();
+        service
+            .ingest(InputResource {
+                content: Some("Different notes".to_string()),
+                ..resource("file:///b.md", &[], &[])
+            })
+            .await
+            .unwrap();
+        let indexable = service
+            .store
+            .select_indexable_resource("file:///b.md")
+            .unwrap()
+            .unwrap();
+        assert_eq!(indexable.content, Some("Different notes".to_string()));
+    }
+
+    #[async_std::test]
+    async fn check_repairs_index() {
+        let service = Service::in_memory().unwrap();
+        service
+            .ingest(InputResource {
+                content: Some("Walking the graph of notes".to_string()),
+                ..resource("file:///a.md", &[], &[])
+            })
+            .await
+            .unwrap();
+        service
+            .index
+            .ingest("file:///ghost.md", "Ghost", "Gone for good", None, &[])
+            .await
+            .unwrap();
+        service.commit().await.unwrap();
+        service.index.forget("file:///a.md").await.unwrap();
+        service.commit().await.unwrap();
+
+        let report = service.check(false).await.unwrap();
+        assert_eq!(report.orphan_documents, vec!["file:///ghost.md"]);
+        assert_eq!(report.unindexed_resources, vec!["file:///a.md"]);
+        assert!(!report.repaired);
+
+        let report = service.check(true).await.unwrap();
+        assert!(report.repaired);
+        assert!(service.check(false).await.unwrap().is_consistent());
+        assert_eq!(service.index.indexed_urls().unwrap(), vec!["file:///a.md"]);
+    }
+
+    #[async_std::test]
+    async fn export_import_round_trip() {
+        let service = Service::in_memory().unwrap();
+        service
+            .ingest(InputResource {
+                content: Some("Walking the graph of notes".to_string()),
+                ..resource("file:///a.md", &["file:///b.md#intro"], &["project/notes"])
+            })
+            .await
+            .unwrap();
+        service
+            .ingest(resource("file:///b.md", &[], &[]))
+            .await
+            .unwrap();
+
+        let mut exported = vec![];
+        assert_eq!(service.export(&mut exported).unwrap(), 2);
+
+        let copy = Service::in_memory().unwrap();
+        assert_eq!(copy.import(&exported[..]).await.unwrap(), 2);
+        let mut reexported = vec![];
+        assert_eq!(copy.export(&mut reexported).unwrap(), 2);
+
+        assert_eq!(
+            String::from_utf8(exported).unwrap(),
+            String::from_utf8(reexported).unwrap()
+        );
+        assert!(copy.check(false).await.unwrap().is_consistent());
+    }
+}
@@ -1,17 +1,26 @@
 use crate::data::{
-    InputLink, InputResource, InputTag, Link, LinkKind, Resource, ResourceInfo, Tag,
+    GraphEdge, GraphResource, IndexableResource, InputLink, InputResource, InputTag, Link,
+    LinkKind, LinkOrder, Page, Resource, ResourceInfo, Revision, RevisionLink, Tag, TagInfo,
+    TagOrder, TAG_SEPARATOR,
 };
+use crate::migration;
+use crate::pagination::Window;
+use async_std::task;
 use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
 use dataloader::cached::Loader;
 use dataloader::BatchFn;
-use dirs;
 pub use juniper::{FieldError, FieldResult};
 use log;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{named_params, Connection, Row};
+use rusqlite::{named_params, Connection, DatabaseName, OpenFlags, Row, Transaction};
 use std::collections::HashMap;
 use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{include_str, io};
+use url::Url;
 
 pub type DecodeResult<T> = Result<T, FieldError>;
 
@@ -31,14 +40,20 @@ where
 
 type Pool = r2d2::Pool<SqliteConnectionManager>;
 
+/// Counter used to give every in-memory database a unique name.
+static IN_MEMORY_DATABASES: AtomicUsize = AtomicUsize::new(0);
+
 pub struct DataStore {
     pool: r2d2::Pool<SqliteConnectionManager>,
 
-    links_by_referrer: Loader<String, Vec<Link>, Error, LinksByReferrer>,
-    links_by_target: Loader<String, Vec<Link>, Error, LinksByTarget>,
+    links_by_referrer: Loader<(String, LinkOrder, Window), Page<Link>, Error, LinksByReferrer>,
+    links_by_target: Loader<(String, LinkOrder, Window), Page<Link>, Error, LinksByTarget>,
     resource_info_by_url: Loader<String, ResourceInfo, Error, ResourceInfoByURL>,
-    tags_by_target: Loader<String, Vec<Tag>, Error, TagsByTarget>,
-    tags_by_name: Loader<String, Vec<Tag>, Error, TagsByName>,
+    tags_by_target: Loader<(String, TagOrder, Window), Page<Tag>, Error, TagsByTarget>,
+    tags_by_name: Loader<(String, TagOrder, Window), Page<Tag>, Error, TagsByName>,
+    related_tags_by_name: Loader<String, Vec<TagInfo>, Error, RelatedTagsByName>,
+    tag_info_by_name: Loader<String, TagInfo, Error, TagInfoByName>,
+    child_tags_by_name: Loader<String, Vec<TagInfo>, Error, ChildTagsByName>,
 }
 
 impl DataStore {
@@ -48,57 +63,279 @@ impl DataStore {
             links_by_referrer: Loader::new(LinksByReferrer::new(&pool)),
             tags_by_target: Loader::new(TagsByTarget::new(&pool)),
             tags_by_name: Loader::new(TagsByName::new(&pool)),
+            related_tags_by_name: Loader::new(RelatedTagsByName::new(&pool)),
+            tag_info_by_name: Loader::new(TagInfoByName::new(&pool)),
+            child_tags_by_name: Loader::new(ChildTagsByName::new(&pool)),
             resource_info_by_url: Loader::new(ResourceInfoByURL::new(&pool)),
             pool: pool,
         }
     }
-    pub fn open() -> io::Result<Self> {
-        let mut path = dirs::home_dir().expect("Unable to locate user home directory");
-        path.push(".knowledge-service");
+    pub fn open(path: &Path) -> io::Result<Self> {
         // Ensure that there is such directory
-        std::fs::create_dir_all(&path)?;
-        path.push("knowledge.sqlite");
-        let manager = SqliteConnectionManager::file(&path).with_init(DataStore::create_tables);
-        let pool = r2d2::Pool::new(manager).expect("Failed to initialize connection manager");
-        let store = DataStore::new(pool);
-        println!("Data base was initialized at {:?}", path.to_str());
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let manager = SqliteConnectionManager::file(path);
+        let store = DataStore::activate(manager)?;
+        log::info!("Data base was initialized at {:?}", path.to_str());
         Ok(store)
     }
+    /// Creates a store backed by an in-memory database which is gone once
+    /// store (and all its clones) are dropped.
+    pub fn in_memory() -> io::Result<Self> {
+        // Every connection to `:memory:` gets its own database, so instead we
+        // use uniquely named in-memory database with shared cache so that all
+        // the connections in the pool share it.
+        let name = format!(
+            "file:knowledge-{:}-{:}?mode=memory&cache=shared",
+            std::process::id(),
+            IN_MEMORY_DATABASES.fetch_add(1, Ordering::SeqCst)
+        );
+        let manager = SqliteConnectionManager::file(name);
+        DataStore::activate(manager)
+    }
+    fn activate(manager: SqliteConnectionManager) -> io::Result<Self> {
+        let manager = manager.with_init(DataStore::init_connection);
+        let pool = r2d2::Pool::new(manager)
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+        // Bring database schema up to date before any queries are issued.
+        let mut connection = pool
+            .get()
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+        let version = migration::migrate(&mut connection)?;
+        log::info!("Data base schema is at version {:}", version);
+        drop(connection);
 
-    pub(crate) fn create_tables(connection: &mut Connection) -> Result<(), rusqlite::Error> {
-        #[allow(unused_must_use)]
-        {
-            // It's ok if this fails. This is temporary workaround due to not using
-            // versions before.
-            connection.execute_batch(include_str!("../sql/migrate_tables.sql"));
-        }
-        Ok(connection.execute_batch(include_str!("../sql/create_tables.sql"))?)
+        Ok(DataStore::new(pool))
     }
-    pub(crate) fn insert_resource(&self, input: &InputResource) -> DecodeResult<Resource> {
+
+    /// Writes consistent copy of the database into a file at `dest` using the
+    /// SQLite online backup API, which is safe while store is being written.
+    pub(crate) fn backup(&self, dest: &Path) -> DecodeResult<()> {
         let connection = self.pool.get()?;
-        let mut insert = connection.prepare_cached(include_str!("../sql/insert_resource.sql"))?;
+        connection.backup(DatabaseName::Main, dest, None)?;
+        Ok(())
+    }
+    pub(crate) fn init_connection(connection: &mut Connection) -> Result<(), rusqlite::Error> {
+        connection.execute_batch(include_str!("../sql/init_connection.sql"))
+    }
+    /// Stores resource along with its tags and links. Everything is written in
+    /// a single transaction so that resource is never left with only part of
+    /// its links or tags.
+    pub(crate) fn ingest(&self, input: &InputResource) -> DecodeResult<Resource> {
+        let mut connection = self.pool.get()?;
+        let transaction = connection.transaction()?;
+        let ingested_at = now();
+        let resource = DataStore::insert_resource(&transaction, input, ingested_at)?;
+        DataStore::record_revision(&transaction, input, ingested_at)?;
+        DataStore::insert_content(&transaction, input)?;
+
+        // Submitted resource is an authoritative source of its tags and links,
+        // so whatever was stored previously is replaced.
+        let no_tags = vec![];
+        let tags = input.tags.as_ref().unwrap_or(&no_tags);
+        DataStore::replace_tags(&transaction, &input.url, tags)?;
+        let no_links = vec![];
+        let links = input.links.as_ref().unwrap_or(&no_links);
+        DataStore::replace_links(&transaction, &input.url, links)?;
+
+        transaction.commit()?;
+        Ok(resource)
+    }
+    fn insert_resource(
+        transaction: &Transaction<'_>,
+        input: &InputResource,
+        ingested_at: i64,
+    ) -> DecodeResult<Resource> {
+        // Resource is created when first seen, so re-ingesting it keeps the
+        // earliest known creation time.
+        let created_at = match (
+            DataStore::select_created_at(transaction, &input.url)?,
+            input.created_at.as_ref().map(encode_time),
+        ) {
+            (Some(stored), Some(given)) => stored.min(given),
+            (stored, given) => stored.or(given).unwrap_or(ingested_at),
+        };
+        let modified_at = input
+            .modified_at
+            .as_ref()
+            .map(encode_time)
+            .unwrap_or(ingested_at);
+
+        let mut insert = transaction.prepare_cached(include_str!("../sql/insert_resource.sql"))?;
         insert.execute_named(named_params! {
           ":url": input.url,
           ":title": input.title,
           ":description": input.description,
           ":cid": input.cid,
           ":icon": input.icon,
-          ":image": input.image
+          ":image": input.image,
+          ":language": input.language,
+          ":has_content": input.content.is_some(),
+          ":created_at": created_at,
+          ":modified_at": modified_at,
+          ":ingested_at": ingested_at
         })?;
 
-        Ok(Resource::from(input))
+        let mut resource = Resource::from(input);
+        if let Some(info) = resource.info.as_mut() {
+            info.created_at = Utc.timestamp_millis_opt(created_at).single();
+            info.modified_at = Utc.timestamp_millis_opt(modified_at).single();
+            info.ingested_at = Utc.timestamp_millis_opt(ingested_at).single();
+        }
+        Ok(resource)
     }
-    pub(crate) fn insert_links(
-        &self,
+    /// Stores content of the resource so that index can be rebuilt from it.
+    /// Content is keyed by cid, so identical content is stored once.
+    fn insert_content(transaction: &Transaction<'_>, input: &InputResource) -> DecodeResult<()> {
+        if let (Some(cid), Some(content)) = (input.cid.as_ref(), input.content.as_ref()) {
+            let mut insert =
+                transaction.prepare_cached(include_str!("../sql/insert_content.sql"))?;
+            insert.execute_named(named_params! {
+                ":cid": cid,
+                ":content": content
+            })?;
+        }
+        Ok(())
+    }
+    /// Records a new revision of the resource unless it is identical to the
+    /// latest recorded one. Returns `true` if revision was recorded.
+    fn record_revision(
+        transaction: &Transaction<'_>,
+        input: &InputResource,
+        recorded_at: i64,
+    ) -> DecodeResult<bool> {
+        let links = revision_links(input);
+        let latest = DataStore::select_latest_revision(transaction, &input.url)?;
+        let version = match latest {
+            Some(ref revision)
+                if revision.cid == input.cid
+                    && revision.title == input.title
+                    && revision.description == input.description
+                    && revision.links == links =>
+            {
+                return Ok(false)
+            }
+            Some(revision) => revision.version + 1,
+            None => 1,
+        };
+        log::info!("Recording revision {:} of {:} in db", version, input.url);
+
+        let mut insert = transaction.prepare_cached(include_str!("../sql/insert_revision.sql"))?;
+        insert.execute_named(named_params! {
+            ":url": input.url,
+            ":version": version,
+            ":cid": input.cid,
+            ":title": input.title,
+            ":description": input.description,
+            ":recorded_at": recorded_at
+        })?;
+        let mut insert_link =
+            transaction.prepare_cached(include_str!("../sql/insert_revision_link.sql"))?;
+        for link in links.iter() {
+            insert_link.execute_named(named_params! {
+                ":url": input.url,
+                ":version": version,
+                ":target_url": link.target_url,
+                ":name": link.name
+            })?;
+        }
+        Ok(true)
+    }
+    fn select_latest_revision(
+        connection: &Connection,
+        url: &str,
+    ) -> DecodeResult<Option<Revision>> {
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_latest_revision.sql"))?;
+        let mut rows = select.query_named(named_params! {":url": url})?;
+        let mut revision = match rows.next()? {
+            Some(row) => Revision::decode_row(&row)?,
+            None => return Ok(None),
+        };
+        let mut links = DataStore::select_revision_links(connection, url, Some(revision.version))?;
+        revision.links = links.remove(&revision.version).unwrap_or_default();
+        Ok(Some(revision))
+    }
+    /// Returns links of the resource revisions grouped by revision version.
+    fn select_revision_links(
+        connection: &Connection,
+        url: &str,
+        version: Option<i32>,
+    ) -> DecodeResult<HashMap<i32, Vec<RevisionLink>>> {
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_revision_links.sql"))?;
+        let mut rows = select.query_named(named_params! {
+            ":url": url,
+            ":version": version
+        })?;
+        let mut links: HashMap<i32, Vec<RevisionLink>> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let version: i32 = row.get(0)?;
+            links
+                .entry(version)
+                .or_insert_with(Vec::new)
+                .push(RevisionLink {
+                    target_url: row.get(1)?,
+                    name: row.get(2)?,
+                });
+        }
+        Ok(links)
+    }
+    /// Returns revisions of the resource, latest first, each annotated with
+    /// links added and removed since the revision before it.
+    pub(crate) fn select_revisions(&self, url: &str) -> DecodeResult<Vec<Revision>> {
+        log::info!("selecting revisions of {:} in db", url);
+        let connection = self.pool.get()?;
+        let mut links = DataStore::select_revision_links(&connection, url, None)?;
+        let mut select = connection.prepare_cached(include_str!("../sql/select_revisions.sql"))?;
+        let mut rows = select.query_named(named_params! {":url": url})?;
+
+        let mut revisions: Vec<Revision> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut revision = Revision::decode_row(&row)?;
+            revision.links = links.remove(&revision.version).unwrap_or_default();
+            let previous = revisions
+                .last()
+                .map(|previous| &previous.links[..])
+                .unwrap_or(&[]);
+            revision.added_links = difference(&revision.links, previous);
+            revision.removed_links = difference(previous, &revision.links);
+            revisions.push(revision);
+        }
+        revisions.reverse();
+        Ok(revisions)
+    }
+    fn select_created_at(transaction: &Transaction<'_>, url: &str) -> DecodeResult<Option<i64>> {
+        let mut select =
+            transaction.prepare_cached(include_str!("../sql/select_resource_created_at.sql"))?;
+        let mut rows = select.query_named(named_params! {":url": url})?;
+        match rows.next()? {
+            Some(row) => Ok(row.get(0)?),
+            None => Ok(None),
+        }
+    }
+    /// Replaces all the outgoing links of the referrer with the given set of
+    /// links. Previously stored links that are not in the set are dropped, so
+    /// that re-ingesting a resource does not accumulate stale links.
+    fn replace_links(
+        transaction: &Transaction<'_>,
         referrer_url: &str,
         links: &Vec<InputLink>,
     ) -> FieldResult<()> {
-        log::info!("Inserting {:} resource links into db", links.len());
-        let connection = self.pool.get()?;
+        log::info!("Replacing resource links with {:} links in db", links.len());
+        let mut delete_inline = transaction
+            .prepare_cached(include_str!("../sql/delete_inline_links_by_referrer.sql"))?;
+        delete_inline.execute_named(named_params! {":referrer_url": referrer_url})?;
+        let mut delete_reference = transaction.prepare_cached(include_str!(
+            "../sql/delete_reference_links_by_referrer.sql"
+        ))?;
+        delete_reference.execute_named(named_params! {":referrer_url": referrer_url})?;
+
         let mut insert_inline =
-            connection.prepare_cached(include_str!("../sql/insert_inline_link.sql"))?;
+            transaction.prepare_cached(include_str!("../sql/insert_inline_link.sql"))?;
         let mut insert_reference =
-            connection.prepare_cached(include_str!("../sql/insert_reference_link.sql"))?;
+            transaction.prepare_cached(include_str!("../sql/insert_reference_link.sql"))?;
 
         for link in links {
             match link.kind {
@@ -133,10 +370,18 @@ impl DataStore {
 
         Ok(())
     }
-    pub(crate) fn insert_tags(&self, target_url: &str, tags: &Vec<InputTag>) -> DecodeResult<()> {
-        log::info!("Inserting {:} resource tags into db", tags.len());
-        let connection = self.pool.get()?;
-        let mut insert = connection.prepare_cached(include_str!("../sql/insert_tag.sql"))?;
+    /// Replaces all the tags of the target with the given set of tags.
+    fn replace_tags(
+        transaction: &Transaction<'_>,
+        target_url: &str,
+        tags: &Vec<InputTag>,
+    ) -> DecodeResult<()> {
+        log::info!("Replacing resource tags with {:} tags in db", tags.len());
+        let mut delete =
+            transaction.prepare_cached(include_str!("../sql/delete_tags_by_target.sql"))?;
+        delete.execute_named(named_params! {":target_url": target_url})?;
+
+        let mut insert = transaction.prepare_cached(include_str!("../sql/insert_tag.sql"))?;
         let no_fragment = String::new();
         for tag in tags {
             insert.execute_named(named_params! {
@@ -147,8 +392,276 @@ impl DataStore {
             })?;
             log::info!("Add #{:} tag to {:}", tag.name, target_url);
         }
+
         Ok(())
     }
+    /// Removes resource along with its tags and outgoing links. All rows are
+    /// removed in a single transaction so either everything about the
+    /// resource is forgotten or nothing is. Returns `false` if there was no
+    /// such resource.
+    pub(crate) fn delete_resource(&self, url: &str) -> DecodeResult<bool> {
+        log::info!("Deleting resource {:} from db", url);
+        let mut connection = self.pool.get()?;
+        let transaction = connection.transaction()?;
+        let deleted = {
+            let mut delete_tags =
+                transaction.prepare_cached(include_str!("../sql/delete_tags_by_target.sql"))?;
+            delete_tags.execute_named(named_params! {":target_url": url})?;
+
+            let mut delete_inline = transaction
+                .prepare_cached(include_str!("../sql/delete_inline_links_by_referrer.sql"))?;
+            delete_inline.execute_named(named_params! {":referrer_url": url})?;
+
+            let mut delete_reference = transaction.prepare_cached(include_str!(
+                "../sql/delete_reference_links_by_referrer.sql"
+            ))?;
+            delete_reference.execute_named(named_params! {":referrer_url": url})?;
+
+            let mut delete_revision_links =
+                transaction.prepare_cached(include_str!("../sql/delete_revision_links.sql"))?;
+            delete_revision_links.execute_named(named_params! {":url": url})?;
+
+            let mut delete_revisions =
+                transaction.prepare_cached(include_str!("../sql/delete_revisions.sql"))?;
+            delete_revisions.execute_named(named_params! {":url": url})?;
+
+            let mut delete_resource =
+                transaction.prepare_cached(include_str!("../sql/delete_resource.sql"))?;
+            let deleted = delete_resource.execute_named(named_params! {":url": url})?;
+
+            // Content may still be shared with other resources or revisions.
+            let mut delete_contents = transaction
+                .prepare_cached(include_str!("../sql/delete_unreferenced_contents.sql"))?;
+            delete_contents.execute(rusqlite::NO_PARAMS)?;
+
+            deleted
+        };
+        transaction.commit()?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Returns URLs of all the stored resources.
+    pub(crate) fn select_resource_urls(&self) -> DecodeResult<Vec<String>> {
+        let connection = self.pool.get()?;
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_resource_urls.sql"))?;
+        let mut rows = select.query(rusqlite::NO_PARAMS)?;
+        let mut urls = Vec::new();
+        while let Some(row) = rows.next()? {
+            urls.push(row.get(0)?);
+        }
+        Ok(urls)
+    }
+    /// Returns URLs of the resources that had content when ingested, along
+    /// with a flag telling whether that content is stored. Resources ingested
+    /// without content are not included.
+    pub(crate) fn select_content_resource_urls(&self) -> DecodeResult<Vec<(String, bool)>> {
+        let connection = self.pool.get()?;
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_content_resource_urls.sql"))?;
+        let mut rows = select.query(rusqlite::NO_PARAMS)?;
+        let mut urls = Vec::new();
+        while let Some(row) = rows.next()? {
+            urls.push((row.get(0)?, row.get(1)?));
+        }
+        Ok(urls)
+    }
+    /// Returns URLs of the referrers that links are stored for, even though
+    /// referrers themselves are not, along with the number of such links.
+    pub(crate) fn select_orphan_links(&self) -> DecodeResult<Vec<(String, usize)>> {
+        self.select_counts(include_str!("../sql/select_orphan_link_referrers.sql"))
+    }
+    /// Returns URLs of the resources that tags are stored for, even though
+    /// resources themselves are not, along with the number of such tags.
+    pub(crate) fn select_orphan_tags(&self) -> DecodeResult<Vec<(String, usize)>> {
+        self.select_counts(include_str!("../sql/select_orphan_tag_targets.sql"))
+    }
+    fn select_counts(&self, sql: &str) -> DecodeResult<Vec<(String, usize)>> {
+        let connection = self.pool.get()?;
+        let mut select = connection.prepare_cached(sql)?;
+        let mut rows = select.query(rusqlite::NO_PARAMS)?;
+        let mut counts = Vec::new();
+        while let Some(row) = rows.next()? {
+            let count: i64 = row.get(1)?;
+            counts.push((row.get(0)?, count as usize));
+        }
+        Ok(counts)
+    }
+    /// Deletes links and tags of the resources that are not stored. Returns
+    /// number of deleted records.
+    pub(crate) fn delete_orphans(&self) -> DecodeResult<usize> {
+        let mut connection = self.pool.get()?;
+        let transaction = connection.transaction()?;
+        let mut deleted = 0;
+        for sql in [
+            include_str!("../sql/delete_orphan_inline_links.sql"),
+            include_str!("../sql/delete_orphan_reference_links.sql"),
+            include_str!("../sql/delete_orphan_tags.sql"),
+        ]
+        .iter()
+        {
+            deleted += transaction.execute(sql, rusqlite::NO_PARAMS)?;
+        }
+        transaction.commit()?;
+        Ok(deleted)
+    }
+    /// Returns edges of the link graph from the resources with the given URLs,
+    /// in the order of URLs. Edge targets are resource URLs, without the
+    /// fragment.
+    pub(crate) fn select_edges_by_referrer(&self, urls: &[String]) -> DecodeResult<Vec<GraphEdge>> {
+        self.select_edges(include_str!("../sql/select_edges_by_referrer.sql"), urls, 0)
+    }
+    /// Returns edges of the link graph to the resources with the given URLs,
+    /// including links to their fragments, in the order of URLs. Edge targets
+    /// are resource URLs, without the fragment.
+    pub(crate) fn select_edges_by_target(&self, urls: &[String]) -> DecodeResult<Vec<GraphEdge>> {
+        self.select_edges(include_str!("../sql/select_edges_by_target.sql"), urls, 1)
+    }
+    fn select_edges(&self, sql: &str, urls: &[String], key: usize) -> DecodeResult<Vec<GraphEdge>> {
+        let mut edges = select_by_keys(&self.pool, sql, urls, |row| {
+            Ok((row.get(key)?, GraphEdge::decode_row(row)?))
+        })?;
+        Ok(urls
+            .iter()
+            .flat_map(|url| edges.remove(url).unwrap_or_default())
+            .collect())
+    }
+    /// Returns all the resources with their tags, ordered by URL.
+    pub(crate) fn select_graph_resources(&self) -> DecodeResult<Vec<GraphResource>> {
+        let connection = self.pool.get()?;
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_graph_resources.sql"))?;
+        let mut rows = select.query(rusqlite::NO_PARAMS)?;
+        let mut resources: Vec<GraphResource> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let url: String = row.get(0)?;
+            let tag: Option<String> = row.get(2)?;
+            match resources.last_mut() {
+                Some(resource) if resource.url == url => resource.tags.extend(tag),
+                _ => {
+                    let title: Option<String> = row.get(1)?;
+                    resources.push(GraphResource {
+                        url,
+                        title: Some(title.unwrap_or_default()),
+                        tags: tag.into_iter().collect(),
+                    })
+                }
+            }
+        }
+        Ok(resources)
+    }
+    /// Returns all the links as edges of the link graph. Unlike the edges of
+    /// the graph queries, links are not deduplicated.
+    pub(crate) fn select_all_edges(&self) -> DecodeResult<Vec<GraphEdge>> {
+        let connection = self.pool.get()?;
+        let mut select = connection.prepare_cached(include_str!("../sql/select_all_edges.sql"))?;
+        let mut rows = select.query(rusqlite::NO_PARAMS)?;
+        GraphEdge::decode_rows(&mut rows)
+    }
+    /// Returns stored resource in the shape it can be ingested again, with
+    /// its content, links and tags, or `None` if there is no such resource.
+    pub(crate) fn select_input_resource(&self, url: &str) -> DecodeResult<Option<InputResource>> {
+        let connection = self.pool.get()?;
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_export_resource.sql"))?;
+        let mut rows = select.query_named(named_params! {":url": url})?;
+        let mut resource = match rows.next()? {
+            Some(row) => {
+                let title: Option<String> = row.get(2)?;
+                let description: Option<String> = row.get(3)?;
+                InputResource {
+                    url: row.get(0)?,
+                    cid: row.get(1)?,
+                    title: title.unwrap_or_default(),
+                    description: description.unwrap_or_default(),
+                    links: None,
+                    tags: None,
+                    icon: row.get(4)?,
+                    image: row.get(5)?,
+                    created_at: decode_time(&row, 6)?,
+                    modified_at: decode_time(&row, 7)?,
+                    language: row.get(8)?,
+                    content: row.get(9)?,
+                }
+            }
+            None => return Ok(None),
+        };
+
+        let keys = [url.to_string()];
+        let links_query = Window::ALL.apply(
+            &include_str!("../sql/select_links_by_referrer.sql")
+                .replace(":order", LinkOrder::TargetUrl.clause()),
+        );
+        let mut links = select_by_keys(&self.pool, &links_query, &keys, |row| {
+            Ok((row.get(1)?, Link::decode_row(row)?))
+        })?;
+        resource.links = links.remove(url).map(|links| {
+            links
+                .into_iter()
+                .map(|link| InputLink {
+                    target_url: link.target_url,
+                    referrer_fragment: link.referrer_fragment,
+                    referrer_location: link.referrer_location,
+                    kind: link.kind,
+                    name: link.name,
+                    title: link.title,
+                    identifier: link.identifier,
+                })
+                .collect()
+        });
+
+        let tags_query = Window::ALL.apply(
+            &include_str!("../sql/select_tags_by_target.sql")
+                .replace(":order", TagOrder::Name.clause()),
+        );
+        let mut tags = select_by_keys(&self.pool, &tags_query, &keys, |row| {
+            Ok((row.get(0)?, Tag::decode_row(row)?))
+        })?;
+        resource.tags = tags.remove(url).map(|tags| {
+            tags.into_iter()
+                .map(|tag| InputTag {
+                    name: tag.name,
+                    target_fragment: tag.target_fragment,
+                    target_location: tag.target_location,
+                })
+                .collect()
+        });
+
+        Ok(Some(resource))
+    }
+    /// Returns stored resource along with its content and tags, or `None` if
+    /// there is no such resource.
+    pub(crate) fn select_indexable_resource(
+        &self,
+        url: &str,
+    ) -> DecodeResult<Option<IndexableResource>> {
+        let connection = self.pool.get()?;
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_indexable_resource.sql"))?;
+        let mut rows = select.query_named(named_params! {":url": url})?;
+        let mut resource = match rows.next()? {
+            Some(row) => {
+                let title: Option<String> = row.get(1)?;
+                IndexableResource {
+                    url: row.get(0)?,
+                    title: title.unwrap_or_default(),
+                    language: row.get(2)?,
+                    content: row.get(3)?,
+                    tags: vec![],
+                }
+            }
+            None => return Ok(None),
+        };
+
+        let mut select_tags =
+            connection.prepare_cached(include_str!("../sql/select_tag_names_by_target.sql"))?;
+        let mut rows = select_tags.query_named(named_params! {":target_url": url})?;
+        while let Some(row) = rows.next()? {
+            resource.tags.push(row.get(0)?);
+        }
+        Ok(Some(resource))
+    }
 
     pub(crate) async fn find_resource_by_url(&self, url: &str) -> DecodeResult<ResourceInfo> {
         self.resource_info_by_url
@@ -157,32 +670,207 @@ impl DataStore {
             .map_err(FieldError::from)
     }
 
-    pub(crate) async fn find_links_by_target(&self, url: &str) -> FieldResult<Vec<Link>> {
+    pub(crate) async fn find_links_by_target(
+        &self,
+        url: &str,
+        order: LinkOrder,
+        window: Window,
+    ) -> FieldResult<Page<Link>> {
         self.links_by_target
-            .load(url.to_string())
+            .load((url.to_string(), order, window))
             .await
             .map_err(FieldError::from)
     }
 
-    pub(crate) async fn find_links_by_referrer(&self, url: &str) -> FieldResult<Vec<Link>> {
+    pub(crate) async fn find_links_by_referrer(
+        &self,
+        url: &str,
+        order: LinkOrder,
+        window: Window,
+    ) -> FieldResult<Page<Link>> {
         self.links_by_referrer
-            .load(url.to_string())
+            .load((url.to_string(), order, window))
             .await
             .map_err(FieldError::from)
     }
 
-    pub(crate) async fn find_tags_by_target(&self, target_url: &str) -> DecodeResult<Vec<Tag>> {
+    pub(crate) async fn find_tags_by_target(
+        &self,
+        target_url: &str,
+        order: TagOrder,
+        window: Window,
+    ) -> DecodeResult<Page<Tag>> {
         self.tags_by_target
-            .load(target_url.to_string())
+            .load((target_url.to_string(), order, window))
             .await
             .map_err(FieldError::from)
     }
-    pub(crate) async fn find_tags_by_name(&self, name: &str) -> DecodeResult<Vec<Tag>> {
-        self.tags_by_name
+    /// Returns distinct tag names ordered by the number of resources they are
+    /// applied to.
+    pub(crate) fn select_all_tags(&self, limit: Option<usize>) -> DecodeResult<Vec<TagInfo>> {
+        log::info!("selecting all tags in db");
+        let connection = self.pool.get()?;
+        let mut select = connection.prepare_cached(include_str!("../sql/select_all_tags.sql"))?;
+        // Negative limit means no limit in SQLite.
+        let limit = limit.map(|limit| limit as i64).unwrap_or(-1);
+        let mut rows = select.query_named(named_params! {":limit": limit})?;
+        TagInfo::decode_rows(&mut rows)
+    }
+    /// Returns resources modified within the given time range, most recently
+    /// modified first.
+    pub(crate) fn select_recent_resources(
+        &self,
+        since: Option<&DateTime<Utc>>,
+        until: Option<&DateTime<Utc>>,
+        limit: Option<usize>,
+    ) -> DecodeResult<Vec<Resource>> {
+        log::info!("selecting recent resources in db");
+        let connection = self.pool.get()?;
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_recent_resources.sql"))?;
+        // Negative limit means no limit in SQLite.
+        let limit = limit.map(|limit| limit as i64).unwrap_or(-1);
+        let mut rows = select.query_named(named_params! {
+            ":since": since.map(encode_time),
+            ":until": until.map(encode_time),
+            ":limit": limit
+        })?;
+        Resource::decode_rows(&mut rows)
+    }
+    /// Returns the window of links to resources that were never ingested.
+    pub(crate) fn select_dangling_links(
+        &self,
+        order: LinkOrder,
+        window: Window,
+    ) -> DecodeResult<Page<Link>> {
+        let connection = self.pool.get()?;
+        let query = window.apply(
+            &include_str!("../sql/select_dangling_links.sql").replace(":order", order.clause()),
+        );
+        // Statement differs with the window, so it is not cached.
+        let mut select = connection.prepare(&query)?;
+        let mut rows = select.query(rusqlite::NO_PARAMS)?;
+        let mut records = Vec::new();
+        while let Some(row) = rows.next()? {
+            records.push(decode_windowed(&row, 17, Link::decode_row(&row)?)?);
+        }
+        Ok(window.page(records))
+    }
+    /// Returns links to `file://` URLs whose files no longer exist. Files are
+    /// checked on a blocking task so that executor is not blocked by them.
+    /// All the file links are selected, as existence of the files can not be
+    /// checked in SQL, so callers have to paginate the result themselves.
+    pub(crate) async fn select_missing_file_links(
+        &self,
+        order: LinkOrder,
+    ) -> DecodeResult<Vec<Link>> {
+        let links = self.select_links(include_str!("../sql/select_file_links.sql"), order)?;
+        Ok(task::spawn_blocking(move || {
+            links
+                .into_iter()
+                .filter(|link| match Url::parse(&link.target_url) {
+                    Ok(url) => match url.to_file_path() {
+                        Ok(path) => !path.exists(),
+                        Err(_) => false,
+                    },
+                    Err(_) => false,
+                })
+                .collect()
+        })
+        .await)
+    }
+    fn select_links(&self, sql: &str, order: LinkOrder) -> DecodeResult<Vec<Link>> {
+        let connection = self.pool.get()?;
+        let mut select = connection.prepare_cached(&sql.replace(":order", order.clause()))?;
+        let mut rows = select.query(rusqlite::NO_PARAMS)?;
+        Link::decode_rows(&mut rows)
+    }
+    /// Returns resources that neither link to nor are linked from any other
+    /// resource, ordered by title.
+    pub(crate) fn select_orphan_resources(
+        &self,
+        limit: Option<usize>,
+    ) -> DecodeResult<Vec<Resource>> {
+        let connection = self.pool.get()?;
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_orphan_resources.sql"))?;
+        // Negative limit means no limit in SQLite.
+        let limit = limit.map(|limit| limit as i64).unwrap_or(-1);
+        let mut rows = select.query_named(named_params! {":limit": limit})?;
+        Resource::decode_rows(&mut rows)
+    }
+    /// Returns resources with a given content identifier, which is to say
+    /// resources with identical content.
+    pub(crate) fn select_resources_by_cid(&self, cid: &str) -> DecodeResult<Vec<Resource>> {
+        log::info!("selecting resources with cid {:} in db", cid);
+        let connection = self.pool.get()?;
+        let mut select =
+            connection.prepare_cached(include_str!("../sql/select_resources_by_cid.sql"))?;
+        let mut rows = select.query_named(named_params! {":cid": cid})?;
+        Resource::decode_rows(&mut rows)
+    }
+    /// Returns tags that are applied to the same resources as the tag with a
+    /// given name, ordered by the number of resources they share.
+    pub(crate) async fn find_related_tags(&self, name: &str) -> DecodeResult<Vec<TagInfo>> {
+        self.related_tags_by_name
+            .load(name.to_string())
+            .await
+            .map_err(FieldError::from)
+    }
+    /// Returns tags with a given name along with the tags nested under it,
+    /// e.g. `project/ksp` and `project/ksp/design` for `project/ksp`.
+    pub(crate) fn select_tags_by_prefix(
+        &self,
+        name: &str,
+        order: TagOrder,
+        window: Window,
+    ) -> DecodeResult<Page<Tag>> {
+        log::info!("selecting tags nested under #{:} in db", name);
+        let connection = self.pool.get()?;
+        let query = window.apply(
+            &include_str!("../sql/select_tags_by_prefix.sql").replace(":order", order.clause()),
+        );
+        // Statement differs with the window, so it is not cached.
+        let mut select = connection.prepare(&query)?;
+        let prefix = format!("{:}{:}", name, TAG_SEPARATOR);
+        let mut rows = select.query_named(named_params! {
+            ":name": name,
+            ":prefix": prefix
+        })?;
+        let mut records = Vec::new();
+        while let Some(row) = rows.next()? {
+            records.push(decode_windowed(&row, 4, Tag::decode_row(&row)?)?);
+        }
+        Ok(window.page(records))
+    }
+    /// Returns tag name with a number of resources it is applied to. Tags that
+    /// are not applied to any resource (like implied parents of nested tags)
+    /// have count of `0`.
+    pub(crate) async fn find_tag_info(&self, name: &str) -> DecodeResult<TagInfo> {
+        self.tag_info_by_name
+            .load(name.to_string())
+            .await
+            .map_err(FieldError::from)
+    }
+    /// Returns tags nested directly under the tag with a given name. Children
+    /// that are only implied by deeper nested tags are included as well.
+    pub(crate) async fn find_child_tags(&self, name: &str) -> DecodeResult<Vec<TagInfo>> {
+        self.child_tags_by_name
             .load(name.to_string())
             .await
             .map_err(FieldError::from)
     }
+    pub(crate) async fn find_tags_by_name(
+        &self,
+        name: &str,
+        order: TagOrder,
+        window: Window,
+    ) -> DecodeResult<Page<Tag>> {
+        self.tags_by_name
+            .load((name.to_string(), order, window))
+            .await
+            .map_err(FieldError::from)
+    }
 }
 
 impl Clone for DataStore {
@@ -202,11 +890,7 @@ impl fmt::Debug for DataStore {
 impl RowDecoder for Link {
     fn decode_row(row: &rusqlite::Row<'_>) -> Result<Self, rusqlite::Error> {
         Ok(Link {
-            kind: match row.get(0)? {
-                0 => LinkKind::Inline,
-                1 => LinkKind::Reference,
-                _ => LinkKind::Inline,
-            },
+            kind: decode_kind(row, 0)?,
             referrer_url: row.get(1)?,
             referrer_cid: row.get(2)?,
             referrer_title: row.get(3)?,
@@ -220,6 +904,22 @@ impl RowDecoder for Link {
             identifier: row.get(10)?,
             name: row.get(11)?,
             title: row.get(12)?,
+
+            referrer_created_at: decode_time(row, 13)?,
+            referrer_modified_at: decode_time(row, 14)?,
+            referrer_ingested_at: decode_time(row, 15)?,
+            referrer_language: row.get(16)?,
+        })
+    }
+}
+
+impl RowDecoder for GraphEdge {
+    fn decode_row(row: &Row<'_>) -> Result<Self, rusqlite::Error> {
+        Ok(GraphEdge {
+            source_url: row.get(0)?,
+            target_url: row.get(1)?,
+            kind: decode_kind(row, 2)?,
+            name: row.get(3)?,
         })
     }
 }
@@ -235,16 +935,283 @@ impl RowDecoder for Tag {
     }
 }
 
+impl RowDecoder for TagInfo {
+    fn decode_row(row: &Row<'_>) -> Result<Self, rusqlite::Error> {
+        let count: i64 = row.get(1)?;
+        Ok(TagInfo {
+            name: row.get(0)?,
+            count: count as usize,
+        })
+    }
+}
+
+impl RowDecoder for Revision {
+    fn decode_row(row: &Row<'_>) -> Result<Self, rusqlite::Error> {
+        let title: Option<String> = row.get(2)?;
+        let description: Option<String> = row.get(3)?;
+        Ok(Revision {
+            version: row.get(0)?,
+            cid: row.get(1)?,
+            title: title.unwrap_or_default(),
+            description: description.unwrap_or_default(),
+            recorded_at: decode_time(row, 4)?.unwrap_or_else(|| Utc.timestamp_millis(0)),
+            links: vec![],
+            added_links: vec![],
+            removed_links: vec![],
+        })
+    }
+}
+
+impl RowDecoder for Resource {
+    fn decode_row(row: &Row<'_>) -> Result<Self, rusqlite::Error> {
+        Ok(Resource {
+            url: row.get(0)?,
+            info: Some(ResourceInfo::decode_row(row)?),
+        })
+    }
+}
+
 impl RowDecoder for ResourceInfo {
     fn decode_row(row: &Row) -> Result<Self, rusqlite::Error> {
         Ok(ResourceInfo {
-            cid: row.get(0)?,
-            title: row.get(1)?,
-            description: row.get(2)?,
-            icon: row.get(3)?,
-            image: row.get(4)?,
+            cid: row.get(1)?,
+            title: row.get(2)?,
+            description: row.get(3)?,
+            icon: row.get(4)?,
+            image: row.get(5)?,
+            created_at: decode_time(row, 6)?,
+            modified_at: decode_time(row, 7)?,
+            ingested_at: decode_time(row, 8)?,
+            language: row.get(9)?,
+        })
+    }
+}
+
+/// SQLite limits number of parameters in a query (999 by default), so larger
+/// batches are split into chunks of this size.
+const MAX_BATCH_SIZE: usize = 500;
+
+/// Expands `:keys` parameter of the query into a list of `n` positional
+/// parameters, because SQLite has no way to bind a list to a single parameter.
+fn expand_keys(sql: &str, n: usize) -> String {
+    sql.replace(":keys", &vec!["?"; n].join(", "))
+}
+
+/// Runs query for the whole batch of keys (in as few round-trips as SQLite
+/// allows) and groups decoded records by the key `decode` maps them to. Every
+/// key of the batch is present in the returned map even if no records were
+/// found for it.
+fn select_by_keys<T, F>(
+    pool: &Pool,
+    sql: &str,
+    keys: &[String],
+    decode: F,
+) -> Result<HashMap<String, Vec<T>>, Error>
+where
+    F: Fn(&Row<'_>) -> Result<(String, T), rusqlite::Error>,
+{
+    let connection = pool.get()?;
+    let mut records: HashMap<String, Vec<T>> =
+        keys.iter().map(|key| (key.clone(), Vec::new())).collect();
+
+    for chunk in keys.chunks(MAX_BATCH_SIZE) {
+        // Statement differs with the size of the batch, so it is not cached as
+        // it would just crowd out the statements that are reused.
+        let mut select = connection.prepare(&expand_keys(sql, chunk.len()))?;
+        let mut rows = select.query(chunk)?;
+        while let Some(row) = rows.next()? {
+            let (key, record) = decode(&row)?;
+            records.entry(key).or_insert_with(Vec::new).push(record);
+        }
+    }
+
+    Ok(records)
+}
+
+/// Maps result of the batch query onto a loader result, where every key gets
+/// a copy of the error if query failed.
+fn batch_result<T>(
+    keys: &[String],
+    result: Result<HashMap<String, T>, Error>,
+) -> HashMap<String, Result<T, Error>> {
+    match result {
+        Ok(mut records) => keys
+            .iter()
+            .map(|key| {
+                let record = records
+                    .remove(key)
+                    .ok_or_else(|| Error::NotFound(key.clone()));
+                (key.clone(), record)
+            })
+            .collect(),
+        Err(error) => keys
+            .iter()
+            .map(|key| (key.clone(), Err(error.clone())))
+            .collect(),
+    }
+}
+
+fn decode_kind(row: &Row<'_>, idx: usize) -> Result<LinkKind, rusqlite::Error> {
+    Ok(match row.get(idx)? {
+        0 => LinkKind::Inline,
+        1 => LinkKind::Reference,
+        _ => LinkKind::Inline,
+    })
+}
+
+/// Copies database at `path` into a file at `dest` without opening a store, so
+/// it can be used while database is open by a running server.
+pub(crate) fn backup(path: &Path, dest: &Path) -> io::Result<()> {
+    // Opened read-only so that missing database is not created.
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|connection| connection.backup(DatabaseName::Main, dest, None))
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+}
+
+/// Replaces database at `path` with the copy from `source`.
+pub(crate) fn restore(path: &Path, source: &Path) -> io::Result<()> {
+    Connection::open(path)
+        .and_then(|mut connection| {
+            connection.restore(
+                DatabaseName::Main,
+                source,
+                None::<fn(rusqlite::backup::Progress)>,
+            )
         })
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+}
+
+/// Ordering of the query results. Since `ORDER BY` can not be parameterized
+/// the `:order` placeholder of the query is substituted with the clause.
+trait OrderBy: Copy + Eq {
+    fn clause(&self) -> &'static str;
+}
+
+impl OrderBy for LinkOrder {
+    fn clause(&self) -> &'static str {
+        match self {
+            LinkOrder::TargetUrl => "target_url, name, referrer_url",
+            LinkOrder::ReferrerTitle => {
+                "referrer_title COLLATE NOCASE, referrer_url, target_url, name"
+            }
+            LinkOrder::IngestTime => "referrer_ingested_at DESC, referrer_url, target_url, name",
+        }
+    }
+}
+
+impl OrderBy for TagOrder {
+    fn clause(&self) -> &'static str {
+        match self {
+            TagOrder::Name => "tags.name, tags.target_url",
+            TagOrder::TargetTitle => "resources.title COLLATE NOCASE, tags.target_url, tags.name",
+            TagOrder::IngestTime => "resources.ingested_at DESC, tags.target_url, tags.name",
+        }
+    }
+}
+
+/// Runs the windowed query for the batch of `(key, order, window)` triples,
+/// issuing a single batch query per distinct order and window. Query must
+/// select position of the record within the key's ordered list and the total
+/// count of records for the key in the columns starting at `position_column`.
+fn select_by_windowed_keys<T, O, F>(
+    pool: &Pool,
+    sql: &str,
+    position_column: usize,
+    keys: &[(String, O, Window)],
+    decode: F,
+) -> HashMap<(String, O, Window), Result<Page<T>, Error>>
+where
+    O: OrderBy + std::hash::Hash,
+    F: Fn(&Row<'_>) -> Result<(String, T), rusqlite::Error>,
+{
+    let mut batches: Vec<(O, Window)> = vec![];
+    for (_, order, window) in keys {
+        if !batches.contains(&(*order, *window)) {
+            batches.push((*order, *window));
+        }
+    }
+
+    let mut results = HashMap::new();
+    for (order, window) in batches {
+        let batch: Vec<String> = keys
+            .iter()
+            .filter(|(_, key_order, key_window)| (*key_order, *key_window) == (order, window))
+            .map(|(key, _, _)| key.clone())
+            .collect();
+        let query = window.apply(&sql.replace(":order", order.clause()));
+        let records = select_by_keys(pool, &query, &batch, |row| {
+            let (key, record) = decode(row)?;
+            Ok((key, decode_windowed(row, position_column, record)?))
+        });
+        let pages = records.map(|records| {
+            records
+                .into_iter()
+                .map(|(key, records)| (key, window.page(records)))
+                .collect()
+        });
+        for (key, result) in batch_result(&batch, pages) {
+            results.insert((key, order, window), result);
+        }
+    }
+    results
+}
+
+/// Pairs the record with its position and total count of records selected by
+/// the windowed query in the columns starting at `position_column`.
+fn decode_windowed<T>(
+    row: &Row<'_>,
+    position_column: usize,
+    record: T,
+) -> Result<(usize, usize, T), rusqlite::Error> {
+    let position: i64 = row.get(position_column)?;
+    let total_count: i64 = row.get(position_column + 1)?;
+    Ok((position as usize, total_count as usize, record))
+}
+
+/// Current time in milliseconds since unix epoch.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Revision records distinct link targets of the resource ordered by URL, so
+/// that link sets of revisions can be compared.
+fn revision_links(input: &InputResource) -> Vec<RevisionLink> {
+    let mut links: Vec<RevisionLink> = vec![];
+    for link in input.links.iter().flatten() {
+        if !links
+            .iter()
+            .any(|known| known.target_url == link.target_url)
+        {
+            links.push(RevisionLink {
+                target_url: link.target_url.clone(),
+                name: link.name.clone(),
+            });
+        }
     }
+    links.sort_by(|a, b| a.target_url.cmp(&b.target_url));
+    links
+}
+
+/// Returns links from `links` that link to targets not in `other`.
+fn difference(links: &[RevisionLink], other: &[RevisionLink]) -> Vec<RevisionLink> {
+    links
+        .iter()
+        .filter(|link| !other.iter().any(|o| o.target_url == link.target_url))
+        .cloned()
+        .collect()
+}
+
+/// Timestamps are stored as milliseconds since the Unix epoch.
+fn encode_time(time: &DateTime<Utc>) -> i64 {
+    time.timestamp_millis()
+}
+
+fn decode_time(row: &Row<'_>, index: usize) -> Result<Option<DateTime<Utc>>, rusqlite::Error> {
+    let millis: Option<i64> = row.get(index)?;
+    Ok(millis.and_then(|millis| Utc.timestamp_millis_opt(millis).single()))
 }
 
 pub struct LinksByTarget {
@@ -254,24 +1221,26 @@ impl LinksByTarget {
     pub fn new(pool: &Pool) -> Self {
         LinksByTarget { pool: pool.clone() }
     }
-    pub fn select(&self, target_url: &str) -> Result<Vec<Link>, FieldError> {
-        log::info!("selecting links by target {:} in db", target_url);
-        let connection = self.pool.get()?;
-
-        let mut select =
-            connection.prepare_cached(include_str!("../sql/select_links_by_target.sql"))?;
-        let mut rows = select.query_named(named_params! {":target_url": target_url})?;
-        Link::decode_rows(&mut rows)
-    }
 }
 
 #[async_trait]
-impl BatchFn<String, Vec<Link>> for LinksByTarget {
+impl BatchFn<(String, LinkOrder, Window), Page<Link>> for LinksByTarget {
     type Error = Error;
-    async fn load(&self, urls: &[String]) -> HashMap<String, Result<Vec<Link>, Self::Error>> {
-        urls.iter()
-            .map(|url| (url.clone(), self.select(url).map_err(Error::from)))
-            .collect()
+    async fn load(
+        &self,
+        keys: &[(String, LinkOrder, Window)],
+    ) -> HashMap<(String, LinkOrder, Window), Result<Page<Link>, Self::Error>> {
+        log::info!("selecting links by {:} targets in db", keys.len());
+        select_by_windowed_keys(
+            &self.pool,
+            include_str!("../sql/select_links_by_target.sql"),
+            17,
+            keys,
+            |row| {
+                let link = Link::decode_row(row)?;
+                Ok((link.target_url.clone(), link))
+            },
+        )
     }
 }
 
@@ -282,22 +1251,25 @@ impl LinksByReferrer {
     pub fn new(pool: &Pool) -> Self {
         LinksByReferrer { pool: pool.clone() }
     }
-    pub fn select(&self, referrer_url: &str) -> Result<Vec<Link>, FieldError> {
-        log::info!("selecting links by referrer {:} in db", referrer_url);
-        let connection = self.pool.get()?;
-        let mut select =
-            connection.prepare_cached(include_str!("../sql/select_links_by_referrer.sql"))?;
-        let mut rows = select.query_named(named_params! {":referrer_url": referrer_url})?;
-        Link::decode_rows(&mut rows)
-    }
 }
 #[async_trait]
-impl BatchFn<String, Vec<Link>> for LinksByReferrer {
+impl BatchFn<(String, LinkOrder, Window), Page<Link>> for LinksByReferrer {
     type Error = Error;
-    async fn load(&self, urls: &[String]) -> HashMap<String, Result<Vec<Link>, Self::Error>> {
-        urls.iter()
-            .map(|url| (url.to_string(), self.select(&url).map_err(Error::from)))
-            .collect()
+    async fn load(
+        &self,
+        keys: &[(String, LinkOrder, Window)],
+    ) -> HashMap<(String, LinkOrder, Window), Result<Page<Link>, Self::Error>> {
+        log::info!("selecting links by {:} referrers in db", keys.len());
+        select_by_windowed_keys(
+            &self.pool,
+            include_str!("../sql/select_links_by_referrer.sql"),
+            17,
+            keys,
+            |row| {
+                let link = Link::decode_row(row)?;
+                Ok((link.referrer_url.clone(), link))
+            },
+        )
     }
 }
 
@@ -309,24 +1281,25 @@ impl TagsByTarget {
     pub fn new(pool: &Pool) -> Self {
         TagsByTarget { pool: pool.clone() }
     }
-    pub fn select(&self, target_url: &str) -> Result<Vec<Tag>, FieldError> {
-        log::info!("selecting tags by target {:} in db", target_url);
-
-        let connection = self.pool.get()?;
-
-        let mut select =
-            connection.prepare_cached(include_str!("../sql/select_tags_by_target.sql"))?;
-        let mut rows = select.query_named(named_params! {":target_url": target_url})?;
-        Tag::decode_rows(&mut rows)
-    }
 }
 #[async_trait]
-impl BatchFn<String, Vec<Tag>> for TagsByTarget {
+impl BatchFn<(String, TagOrder, Window), Page<Tag>> for TagsByTarget {
     type Error = Error;
-    async fn load(&self, urls: &[String]) -> HashMap<String, Result<Vec<Tag>, Self::Error>> {
-        urls.iter()
-            .map(|url| (url.clone(), self.select(&url).map_err(Error::from)))
-            .collect()
+    async fn load(
+        &self,
+        keys: &[(String, TagOrder, Window)],
+    ) -> HashMap<(String, TagOrder, Window), Result<Page<Tag>, Self::Error>> {
+        log::info!("selecting tags by {:} targets in db", keys.len());
+        select_by_windowed_keys(
+            &self.pool,
+            include_str!("../sql/select_tags_by_target.sql"),
+            4,
+            keys,
+            |row| {
+                let tag = Tag::decode_row(row)?;
+                Ok((tag.target_url.clone(), tag))
+            },
+        )
     }
 }
 
@@ -338,42 +1311,155 @@ impl TagsByName {
     pub fn new(pool: &Pool) -> Self {
         TagsByName { pool: pool.clone() }
     }
-    pub fn select(&self, name: &str) -> Result<Vec<Tag>, FieldError> {
-        log::info!("selecting tags by name #{:} in db", name);
+}
+#[async_trait]
+impl BatchFn<(String, TagOrder, Window), Page<Tag>> for TagsByName {
+    type Error = Error;
+    async fn load(
+        &self,
+        keys: &[(String, TagOrder, Window)],
+    ) -> HashMap<(String, TagOrder, Window), Result<Page<Tag>, Self::Error>> {
+        log::info!("selecting tags by {:} names in db", keys.len());
+        select_by_windowed_keys(
+            &self.pool,
+            include_str!("../sql/select_tags_by_name.sql"),
+            4,
+            keys,
+            |row| {
+                let tag = Tag::decode_row(row)?;
+                Ok((tag.name.clone(), tag))
+            },
+        )
+    }
+}
+pub struct RelatedTagsByName {
+    pool: Pool,
+}
 
-        let connection = self.pool.get()?;
+impl RelatedTagsByName {
+    pub fn new(pool: &Pool) -> Self {
+        RelatedTagsByName { pool: pool.clone() }
+    }
+}
+#[async_trait]
+impl BatchFn<String, Vec<TagInfo>> for RelatedTagsByName {
+    type Error = Error;
+    async fn load(&self, names: &[String]) -> HashMap<String, Result<Vec<TagInfo>, Self::Error>> {
+        log::info!("selecting tags related to {:} tags in db", names.len());
+        let tags = select_by_keys(
+            &self.pool,
+            include_str!("../sql/select_related_tags.sql"),
+            names,
+            |row| {
+                let count: i64 = row.get(2)?;
+                let tag = TagInfo {
+                    name: row.get(1)?,
+                    count: count as usize,
+                };
+                Ok((row.get(0)?, tag))
+            },
+        );
+        batch_result(names, tags)
+    }
+}
 
-        let mut select =
-            connection.prepare_cached(include_str!("../sql/select_tags_by_name.sql"))?;
-        let mut rows = select.query_named(named_params! {":name": name})?;
-        Tag::decode_rows(&mut rows)
+pub struct TagInfoByName {
+    pool: Pool,
+}
+
+impl TagInfoByName {
+    pub fn new(pool: &Pool) -> Self {
+        TagInfoByName { pool: pool.clone() }
     }
 }
 #[async_trait]
-impl BatchFn<String, Vec<Tag>> for TagsByName {
+impl BatchFn<String, TagInfo> for TagInfoByName {
     type Error = Error;
-    async fn load(&self, names: &[String]) -> HashMap<String, Result<Vec<Tag>, Self::Error>> {
-        names
-            .iter()
-            .map(|name| (name.clone(), self.select(&name).map_err(Error::from)))
-            .collect()
+    async fn load(&self, names: &[String]) -> HashMap<String, Result<TagInfo, Self::Error>> {
+        log::info!("selecting {:} tag counts in db", names.len());
+        let tags = select_by_keys(
+            &self.pool,
+            include_str!("../sql/select_tag_info_by_name.sql"),
+            names,
+            |row| {
+                let tag = TagInfo::decode_row(row)?;
+                Ok((tag.name.clone(), tag))
+            },
+        )
+        .map(|tags| {
+            tags.into_iter()
+                .map(|(name, mut infos)| {
+                    let info = infos.pop().unwrap_or_else(|| TagInfo {
+                        name: name.clone(),
+                        count: 0,
+                    });
+                    (name, info)
+                })
+                .collect()
+        });
+        batch_result(names, tags)
     }
 }
-pub struct ResourceInfoByURL {
+
+pub struct ChildTagsByName {
     pool: Pool,
 }
-impl ResourceInfoByURL {
+
+impl ChildTagsByName {
     pub fn new(pool: &Pool) -> Self {
-        ResourceInfoByURL { pool: pool.clone() }
+        ChildTagsByName { pool: pool.clone() }
     }
-    pub fn select(&self, url: &str) -> Result<ResourceInfo, FieldError> {
-        log::info!("selecting a resource in db{:}", url);
+    /// Loads all the nested tags, which is then used to derive children for
+    /// the whole batch.
+    fn select_nested(&self) -> Result<Vec<TagInfo>, Error> {
         let connection = self.pool.get()?;
         let mut select =
-            connection.prepare_cached(include_str!("../sql/select_resource_by_url.sql"))?;
-        let info = select.query_row_named(named_params! {":url": url}, ResourceInfo::decode_row)?;
+            connection.prepare_cached(include_str!("../sql/select_nested_tags.sql"))?;
+        let mut rows = select.query(rusqlite::NO_PARAMS)?;
+        Ok(TagInfo::decode_rows(&mut rows)?)
+    }
+}
+#[async_trait]
+impl BatchFn<String, Vec<TagInfo>> for ChildTagsByName {
+    type Error = Error;
+    async fn load(&self, names: &[String]) -> HashMap<String, Result<Vec<TagInfo>, Self::Error>> {
+        log::info!("selecting child tags of {:} tags in db", names.len());
+        let children = self.select_nested().map(|nested| {
+            names
+                .iter()
+                .map(|name| {
+                    let prefix = format!("{:}{:}", name, TAG_SEPARATOR);
+                    let mut children: Vec<TagInfo> = vec![];
+                    for tag in nested.iter() {
+                        if !tag.name.starts_with(&prefix) || tag.name.len() == prefix.len() {
+                            continue;
+                        }
+                        let rest = &tag.name[prefix.len()..];
+                        let segment = rest.split(TAG_SEPARATOR).next().unwrap_or(rest);
+                        let child = format!("{:}{:}", prefix, segment);
+                        // Count only applies to the child itself, deeper
+                        // nested tags just imply its presence.
+                        let count = if tag.name == child { tag.count } else { 0 };
+                        match children.iter_mut().find(|info| info.name == child) {
+                            Some(info) => info.count += count,
+                            None => children.push(TagInfo { name: child, count }),
+                        }
+                    }
+                    children.sort_by(|a, b| a.name.cmp(&b.name));
+                    (name.clone(), children)
+                })
+                .collect()
+        });
+        batch_result(names, children)
+    }
+}
 
-        Ok(info)
+pub struct ResourceInfoByURL {
+    pool: Pool,
+}
+impl ResourceInfoByURL {
+    pub fn new(pool: &Pool) -> Self {
+        ResourceInfoByURL { pool: pool.clone() }
     }
 }
 
@@ -381,15 +1467,29 @@ impl ResourceInfoByURL {
 impl BatchFn<String, ResourceInfo> for ResourceInfoByURL {
     type Error = Error;
     async fn load(&self, urls: &[String]) -> HashMap<String, Result<ResourceInfo, Self::Error>> {
-        urls.iter()
-            .map(|url| (url.to_string(), self.select(url).map_err(Error::from)))
-            .collect()
+        log::info!("selecting {:} resources in db", urls.len());
+        let resources = select_by_keys(
+            &self.pool,
+            include_str!("../sql/select_resources_by_url.sql"),
+            urls,
+            |row| Ok((row.get(0)?, ResourceInfo::decode_row(row)?)),
+        )
+        .map(|resources| {
+            // Resources that were not found are left out so they map to
+            // `Error::NotFound`.
+            resources
+                .into_iter()
+                .filter_map(|(url, mut infos)| infos.pop().map(|info| (url, info)))
+                .collect()
+        });
+        batch_result(urls, resources)
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum Error {
     SQLError(String),
+    NotFound(String),
 }
 impl From<FieldError> for Error {
     fn from(error: FieldError) -> Self {
@@ -401,10 +1501,229 @@ impl From<rusqlite::Error> for Error {
         Error::SQLError(format!("{:}", error))
     }
 }
+impl From<r2d2::Error> for Error {
+    fn from(error: r2d2::Error) -> Self {
+        Error::SQLError(format!("{:}", error))
+    }
+}
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::SQLError(error) => error.fmt(f),
+            Error::NotFound(key) => write!(f, "No record was found for {:}", key),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::resource;
+    use futures::future::join_all;
+
+    fn targets(page: &Page<Link>) -> Vec<&str> {
+        page.edges
+            .iter()
+            .map(|(_, link)| link.target_url.as_str())
+            .collect()
+    }
+
+    fn names(page: &Page<Tag>) -> Vec<&str> {
+        page.edges
+            .iter()
+            .map(|(_, tag)| tag.name.as_str())
+            .collect()
+    }
+
+    #[async_std::test]
+    async fn reingest_replaces_links_and_tags() {
+        let store = DataStore::in_memory().unwrap();
+        store
+            .ingest(&resource(
+                "file:///a.md",
+                &["file:///b.md", "file:///c.md"],
+                &["x", "y"],
+            ))
+            .unwrap();
+        store
+            .ingest(&resource(
+                "file:///a.md",
+                &["file:///c.md", "file:///d.md"],
+                &["y", "z"],
+            ))
+            .unwrap();
+
+        let links = store
+            .find_links_by_referrer("file:///a.md", LinkOrder::TargetUrl, Window::ALL)
+            .await
+            .unwrap();
+        assert_eq!(targets(&links), vec!["file:///c.md", "file:///d.md"]);
+        let tags = store
+            .find_tags_by_target("file:///a.md", TagOrder::Name, Window::ALL)
+            .await
+            .unwrap();
+        assert_eq!(names(&tags), vec!["y", "z"]);
+        let back_links = store
+            .find_links_by_target("file:///b.md", LinkOrder::TargetUrl, Window::ALL)
+            .await
+            .unwrap();
+        assert_eq!(back_links.total_count, 0);
+    }
+
+    #[async_std::test]
+    async fn batch_loads_results_by_key() {
+        let store = DataStore::in_memory().unwrap();
+        store
+            .ingest(&resource("file:///a.md", &["file:///c.md"], &[]))
+            .unwrap();
+        store
+            .ingest(&resource(
+                "file:///b.md",
+                &["file:///c.md", "file:///d.md"],
+                &[],
+            ))
+            .unwrap();
+
+        let keys: Vec<(String, LinkOrder, Window)> =
+            ["file:///b.md", "file:///none.md", "file:///a.md"]
+                .iter()
+                .map(|url| (url.to_string(), LinkOrder::TargetUrl, Window::ALL))
+                .collect();
+        let mut pages = LinksByReferrer::new(&store.pool).load(&keys).await;
+        assert_eq!(pages.len(), keys.len());
+        let b = pages.remove(&keys[0]).unwrap().unwrap();
+        assert_eq!(targets(&b), vec!["file:///c.md", "file:///d.md"]);
+        let none = pages.remove(&keys[1]).unwrap().unwrap();
+        assert_eq!(none.total_count, 0);
+        let a = pages.remove(&keys[2]).unwrap().unwrap();
+        assert_eq!(targets(&a), vec!["file:///c.md"]);
+
+        // Concurrent loads are batched and each gets results of its own key.
+        let urls = ["file:///d.md", "file:///c.md"];
+        let pages = join_all(
+            urls.iter()
+                .map(|url| store.find_links_by_target(url, LinkOrder::ReferrerTitle, Window::ALL)),
+        )
+        .await;
+        let referrers: Vec<Vec<String>> = pages
+            .into_iter()
+            .map(|page| {
+                page.unwrap()
+                    .edges
+                    .into_iter()
+                    .map(|(_, link)| link.referrer_url)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            referrers,
+            vec![
+                vec!["file:///b.md".to_string()],
+                vec!["file:///a.md".to_string(), "file:///b.md".to_string()]
+            ]
+        );
+    }
+
+    #[async_std::test]
+    async fn paginates_with_cursors() {
+        let store = DataStore::in_memory().unwrap();
+        store
+            .ingest(&resource("file:///a.md", &[], &["a", "b", "c", "d", "e"]))
+            .unwrap();
+
+        let window = Window::new(Some(2), Some("0".to_string())).unwrap();
+        let page = store
+            .find_tags_by_target("file:///a.md", TagOrder::Name, window)
+            .await
+            .unwrap();
+        assert_eq!(names(&page), vec!["b", "c"]);
+        assert_eq!(page.total_count, 5);
+        assert!(page.page_info.has_previous_page);
+        assert!(page.page_info.has_next_page);
+        assert_eq!(page.page_info.start_cursor, Some("1".to_string()));
+        assert_eq!(page.page_info.end_cursor, Some("2".to_string()));
+
+        let window = Window::new(Some(2), page.page_info.end_cursor.clone()).unwrap();
+        let page = store
+            .find_tags_by_target("file:///a.md", TagOrder::Name, window)
+            .await
+            .unwrap();
+        assert_eq!(names(&page), vec!["d", "e"]);
+        assert!(!page.page_info.has_next_page);
+
+        // Total count is known even when window is past the end.
+        let window = Window::new(None, Some("9".to_string())).unwrap();
+        let page = store
+            .find_tags_by_target("file:///a.md", TagOrder::Name, window)
+            .await
+            .unwrap();
+        assert!(page.edges.is_empty());
+        assert_eq!(page.total_count, 5);
+
+        let window = Window::new(Some(0), None).unwrap();
+        let page = store
+            .select_tags_by_prefix("a", TagOrder::Name, window)
+            .unwrap();
+        assert!(page.edges.is_empty());
+        assert_eq!(page.total_count, 1);
+
+        assert!(Window::new(Some(-1), None).is_err());
+        assert!(Window::new(None, Some("first".to_string())).is_err());
+        assert!(Window::new(None, Some(usize::MAX.to_string())).is_err());
+    }
+
+    #[async_std::test]
+    async fn reports_dangling_links_and_orphans() {
+        let store = DataStore::in_memory().unwrap();
+        let existing = Url::from_directory_path(std::env::temp_dir())
+            .unwrap()
+            .to_string();
+        store
+            .ingest(&resource(
+                "file:///a.md",
+                &[
+                    "file:///b.md",
+                    "file:///c.md#section",
+                    "file:///d.md.bak",
+                    "file:///missing/e.md",
+                    existing.as_str(),
+                ],
+                &[],
+            ))
+            .unwrap();
+        store.ingest(&resource("file:///c.md", &[], &[])).unwrap();
+        store.ingest(&resource("file:///d.md", &[], &[])).unwrap();
+        store.ingest(&resource(&existing, &[], &[])).unwrap();
+
+        let page = store
+            .select_dangling_links(LinkOrder::TargetUrl, Window::ALL)
+            .unwrap();
+        assert_eq!(
+            targets(&page),
+            vec!["file:///b.md", "file:///d.md.bak", "file:///missing/e.md"]
+        );
+        let window = Window::new(Some(1), Some("0".to_string())).unwrap();
+        let page = store
+            .select_dangling_links(LinkOrder::TargetUrl, window)
+            .unwrap();
+        assert_eq!(targets(&page), vec!["file:///d.md.bak"]);
+        assert_eq!(page.total_count, 3);
+        assert!(page.page_info.has_next_page);
+
+        let missing = store
+            .select_missing_file_links(LinkOrder::TargetUrl)
+            .await
+            .unwrap();
+        let missing: Vec<&str> = missing
+            .iter()
+            .map(|link| link.target_url.as_str())
+            .collect();
+        assert!(missing.contains(&"file:///missing/e.md"));
+        assert!(!missing.contains(&existing.as_str()));
+
+        // `c.md` is linked to through a fragment and `d.md.bak` is not `d.md`.
+        let orphans = store.select_orphan_resources(None).unwrap();
+        let orphans: Vec<&str> = orphans.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(orphans, vec!["file:///d.md"]);
+    }
+}
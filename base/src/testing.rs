@@ -0,0 +1,44 @@
+//! Fixtures shared by the unit tests.
+
+use crate::data::{InputLink, InputResource, InputTag, LinkKind};
+
+/// Resource at `url` titled after it, with inline links to `links` named after
+/// their targets and with `tags`. It has no content, which tests can set with
+/// the struct update syntax.
+pub fn resource(url: &str, links: &[&str], tags: &[&str]) -> InputResource {
+    InputResource {
+        url: url.to_string(),
+        cid: None,
+        title: url.to_string(),
+        description: String::new(),
+        links: Some(
+            links
+                .iter()
+                .map(|target_url| InputLink {
+                    target_url: target_url.to_string(),
+                    referrer_fragment: None,
+                    referrer_location: None,
+                    kind: LinkKind::Inline,
+                    name: target_url.to_string(),
+                    title: String::new(),
+                    identifier: None,
+                })
+                .collect(),
+        ),
+        tags: Some(
+            tags.iter()
+                .map(|name| InputTag {
+                    name: name.to_string(),
+                    target_fragment: None,
+                    target_location: None,
+                })
+                .collect(),
+        ),
+        icon: None,
+        image: None,
+        created_at: None,
+        modified_at: None,
+        language: None,
+        content: None,
+    }
+}
@@ -1,29 +1,51 @@
 #![feature(proc_macro_hygiene)]
 
 use commander_rust::{command, entry, option, run, Cli};
-use dirs;
 use env_logger;
+use knowledge_server_base::backup;
+use knowledge_server_base::config::Config;
+use knowledge_server_base::graph;
 use knowledge_server_base::server;
+use knowledge_server_base::service;
+use knowledge_server_base::service::Service;
 use knowledge_server_scanner::scanner;
 use std::env;
-use std::io::Result;
+use std::fs::File;
+use std::io::{stdout, BufReader, BufWriter, Result};
+use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use syntax::wait;
 
+/// Resolves knowledge base location from the `--data-dir` and `--profile`
+/// options, falling back to the environment variables and the defaults.
+fn config(cli: &Cli) -> Result<Config> {
+    let data_dir = if cli.has("data-dir") {
+        Some(PathBuf::from(cli.get_or("data-dir", format!(""))))
+    } else {
+        None
+    };
+    let profile = if cli.has("profile") {
+        Some(cli.get_or("profile", format!("")))
+    } else {
+        None
+    };
+    Config::new(data_dir, profile)
+}
+
 #[option(-p, --port <port>, "Port to be used by the knowledge-server (Default 8080)")]
 #[option(-o, --out <path>, "Path where service log is written")]
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
 #[command(daemon, "Run server in the background")]
 fn daemon(cli: Cli) -> Result<()> {
+    let config = config(&cli)?;
     let path = if cli.has("out") {
         let mut path = env::current_dir()?;
         path.push(cli.get_or("out", format!("")));
         path
     } else {
-        let mut path = dirs::home_dir().expect("Unable to locate user home directory");
-        path.push(".knowledge-service");
-        std::fs::create_dir_all(&path)?;
-        path.push("service.log");
-        path
+        std::fs::create_dir_all(config.data_dir())?;
+        config.log_path()
     };
 
     let port = cli.get_or("port", format!("8080"));
@@ -34,6 +56,9 @@ fn daemon(cli: Cli) -> Result<()> {
         .arg("serve")
         .arg("--port")
         .arg(port)
+        // Profile is already resolved into the data directory.
+        .arg("--data-dir")
+        .arg(config.data_dir())
         // So that if anything is read from standard input, it will crash does to
         // EOF immediately.
         .stdin(Stdio::null())
@@ -48,11 +73,14 @@ fn daemon(cli: Cli) -> Result<()> {
 
 #[wait]
 #[option(-p, --port <port>, "Port to be used by the knowledge-server (Default 8080)")]
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
 #[command(serve, "Run server in the foreground")]
 async fn serve(cli: Cli) -> Result<()> {
+    let config = config(&cli)?;
     let port = cli.get_or("port", format!("8080"));
     let address = format!("127.0.0.1:{:}", port);
-    server::activate(&address).await?;
+    server::activate(&address, &config).await?;
     println!("Starting server http://{}", address);
     Ok(())
 }
@@ -60,8 +88,11 @@ async fn serve(cli: Cli) -> Result<()> {
 #[wait]
 #[option(-n, --dry-run, "Don't actually add the file(s), just show.")]
 #[option(-t, --tag <tags>, "Comma delimited list of tags applied to all findings.")]
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
 #[command(scan <path>, "Scans directory and submits all findings to knowledge-server")]
 async fn scan(path: String, cli: Cli) -> Result<()> {
+    let config = config(&cli)?;
     // Resolve the given path.
     let mut base = env::current_dir()?;
     base.push(path);
@@ -75,12 +106,146 @@ async fn scan(path: String, cli: Cli) -> Result<()> {
         .collect();
 
     let dry_run = cli.has("dry-run");
-    let n = scanner::scan(&base, &tags, dry_run).await?;
+    let n = scanner::scan(&base, &tags, dry_run, &config).await?;
     println!("Ingested {:} files", n);
 
     Ok(())
 }
 
+#[wait]
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
+#[command(forget <url>, "Removes resource with the given URL from the knowledge base")]
+async fn forget(url: String, cli: Cli) -> Result<()> {
+    let service = Service::new(&config(&cli)?)?;
+    if service.forget(&url).await? {
+        println!("Forgot {:}", url);
+    } else {
+        println!("No resource {:} in the knowledge base", url);
+    }
+
+    Ok(())
+}
+
+#[wait]
+#[option(-r, --recreate, "Discard the existing index first, e.g. when it is corrupted or outdated")]
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
+#[command(reindex, "Rebuilds full-text index from the stored contents")]
+async fn reindex(cli: Cli) -> Result<()> {
+    let config = config(&cli)?;
+    let service = if cli.has("recreate") {
+        Service::with_new_index(&config)?
+    } else {
+        Service::new(&config)?
+    };
+    let report = service
+        .reindex(|done, total, url| println!("[{:}/{:}] {:}", done, total, url))
+        .await?;
+    println!("Reindexed {:} resources", report.indexed);
+    if report.skipped > 0 {
+        println!(
+            "Skipped {:} resources without stored content, scan them again to index:",
+            report.skipped
+        );
+        for url in report.skipped_urls.iter() {
+            println!("  {:}", url);
+        }
+    }
+
+    Ok(())
+}
+
+#[wait]
+#[option(-r, --repair, "Repair discrepancies that were found")]
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
+#[command(check, "Checks that the store and the full-text index are consistent")]
+async fn check(cli: Cli) -> Result<()> {
+    let service = Service::new(&config(&cli)?)?;
+    let report = service.check(cli.has("repair")).await?;
+    print!("{:}", report);
+    if !report.is_consistent() && !report.repaired {
+        println!("Run with --repair to fix discrepancies");
+    }
+
+    Ok(())
+}
+
+#[option(-o, --out <path>, "Path where export is written (Default standard output)")]
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
+#[command(export, "Exports all resources as newline-delimited JSON")]
+fn export(cli: Cli) -> Result<()> {
+    let config = config(&cli)?;
+    if cli.has("out") {
+        let path = cli.get_or("out", format!(""));
+        let n = service::export(&config, BufWriter::new(File::create(&path)?))?;
+        println!("Exported {:} resources to {:}", n, path);
+    } else {
+        service::export(&config, BufWriter::new(stdout()))?;
+    }
+
+    Ok(())
+}
+
+#[wait]
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
+#[command(import <path>, "Imports resources from the newline-delimited JSON export")]
+async fn import(path: String, cli: Cli) -> Result<()> {
+    let service = Service::new(&config(&cli)?)?;
+    let n = service.import(BufReader::new(File::open(&path)?)).await?;
+    println!("Imported {:} resources", n);
+
+    Ok(())
+}
+
+#[option(-f, --format <format>, "Format of the graph: dot, gexf or graphml (Default graphml)")]
+#[option(-o, --out <path>, "Path where graph is written (Default standard output)")]
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
+#[command(graph, "Exports the link graph for Graphviz, Gephi and other tools")]
+fn graph(cli: Cli) -> Result<()> {
+    let config = config(&cli)?;
+    let format: graph::Format = cli.get_or("format", format!("graphml")).parse()?;
+    if cli.has("out") {
+        let path = cli.get_or("out", format!(""));
+        service::export_graph(&config, format, BufWriter::new(File::create(&path)?))?;
+        println!("Exported link graph to {:}", path);
+    } else {
+        service::export_graph(&config, format, BufWriter::new(stdout()))?;
+    }
+
+    Ok(())
+}
+
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
+#[command(backup <dest>, "Takes a consistent snapshot of the knowledge base into a directory")]
+fn backup(dest: String, cli: Cli) -> Result<()> {
+    let config = config(&cli)?;
+    let mut path = env::current_dir()?;
+    path.push(dest);
+    let snapshot = backup::backup(&config, &path)?;
+    println!("Snapshot was written to {:}", snapshot.path);
+
+    Ok(())
+}
+
+#[option(-d, --data-dir <path>, "Directory where knowledge base is stored (Default ~/.knowledge-service)")]
+#[option(-P, --profile <name>, "Name of the knowledge base profile to use")]
+#[command(restore <source>, "Replaces knowledge base with the snapshot, server must be stopped")]
+fn restore(source: String, cli: Cli) -> Result<()> {
+    let config = config(&cli)?;
+    let mut path = env::current_dir()?;
+    path.push(source);
+    backup::restore(&config, &path)?;
+    println!("Knowledge base was restored from {:?}", path);
+
+    Ok(())
+}
+
 #[wait]
 #[entry]
 async fn main() -> Result<()> {
@@ -51,28 +51,44 @@ fn decode_tags(value: &Yaml) -> Option<Vec<String>> {
 }
 
 fn decode_tags_array(value: &Yaml) -> Option<Vec<String>> {
-    value.as_vec().as_ref().map(|vec| {
-        vec.into_iter()
-            .filter_map(decode_tag)
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect()
-    })
+    value
+        .as_vec()
+        .as_ref()
+        .map(|vec| vec.into_iter().filter_map(decode_tag).collect())
 }
 
 fn decode_tags_string(value: &Yaml) -> Option<Vec<String>> {
-    value.as_str().as_ref().map(|s| {
-        s.rsplit(",")
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect()
-    })
+    value
+        .as_str()
+        .as_ref()
+        .map(|s| s.rsplit(",").filter_map(normalize_tag).collect())
 }
 
-fn decode_tag(value: &Yaml) -> Option<&str> {
-    if let Yaml::String(tag) = value {
-        Some(tag)
-    } else {
+/// Decodes tag from either a string like `project/ksp/design` or an array of
+/// path segments like `[project, ksp, design]`.
+fn decode_tag(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(tag) => normalize_tag(tag),
+        Yaml::Array(segments) => {
+            let path: Vec<&str> = segments.iter().filter_map(|s| s.as_str()).collect();
+            normalize_tag(&path.join("/"))
+        }
+        _ => None,
+    }
+}
+
+/// Normalizes hierarchical tag by trimming each path segment and dropping
+/// empty ones, so that ` project / ksp/ ` becomes `project/ksp`.
+fn normalize_tag(tag: &str) -> Option<String> {
+    let segments: Vec<&str> = tag
+        .split('/')
+        .map(|segment| segment.trim())
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    if segments.is_empty() {
         None
+    } else {
+        Some(segments.join("/"))
     }
 }
@@ -1,5 +1,6 @@
 use crate::frontmatter::read_metadata;
 use crate::resource::Resource;
+use chrono::{DateTime, Utc};
 use core::ops::Range;
 use knowledge_server_base::data::{InputLink, InputResource, InputTag, LinkKind};
 use pulldown_cmark::{Event as Token, LinkType, Parser, Tag as Span};
@@ -13,6 +14,11 @@ pub async fn read(resource: &Resource) -> Result<InputResource> {
     let tags = metadata
         .tags
         .map(|tags| tags.into_iter().map(InputTag::from).collect());
+    // Creation time is not available on all platforms & file systems, in which
+    // case knowledge base falls back to the time resource was first ingested.
+    let stat = resource.metadata().await?;
+    let created_at = stat.created().ok().map(DateTime::<Utc>::from);
+    let modified_at = stat.modified().ok().map(DateTime::<Utc>::from);
 
     let resource = InputResource {
         url: resource.url().to_string(),
@@ -26,6 +32,9 @@ pub async fn read(resource: &Resource) -> Result<InputResource> {
             .unwrap_or(format!("")),
         icon: None,
         image: None,
+        created_at,
+        modified_at,
+        language: None,
         content: Some(content),
     };
 
@@ -1,4 +1,4 @@
-use async_std::fs::File;
+use async_std::fs::{File, Metadata};
 use async_std::io::{Error, ErrorKind, Read, ReadExt, Result, Seek};
 pub use std::convert::TryFrom;
 use std::fmt;
@@ -28,6 +28,13 @@ impl Resource {
     }
   }
 
+  pub async fn metadata(&self) -> Result<Metadata> {
+    match self {
+      // .unwrap is fine as we know it's file:// url
+      Resource::File(url) => async_std::fs::metadata(url.to_file_path().unwrap()).await,
+    }
+  }
+
   pub async fn read_to_string<'a>(&self, buf: &'a mut String) -> Result<usize> {
     let mut reader = self.reader().await?;
     reader.read_to_string(buf).await
@@ -2,6 +2,7 @@ use crate::markdown;
 use crate::resource::Resource;
 use async_std::io;
 use ignore::DirEntry;
+use knowledge_server_base::config::Config;
 use knowledge_server_base::data::InputTag;
 // use knowledge_server_base::schema::{FieldError, Mutations, State};
 use knowledge_server_base::service::Service;
@@ -38,10 +39,15 @@ pub struct ScanReport {
     pub tags: usize,
 }
 
-pub async fn scan<'a>(path: &Path, tags: &Vec<&str>, dry_run: bool) -> io::Result<usize> {
+pub async fn scan<'a>(
+    path: &Path,
+    tags: &Vec<&str>,
+    dry_run: bool,
+    config: &Config,
+) -> io::Result<usize> {
     let entries = walk(path);
     let mut n = 0;
-    let service = Service::new()?;
+    let service = Service::new(config)?;
 
     for entry in entries {
         let path = entry.path();